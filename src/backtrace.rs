@@ -0,0 +1,189 @@
+//! Stack backtraces, following the chain of saved frame pointers.
+//!
+//! The target spec keeps the frame pointer in every function, so each frame starts with the
+//! caller's `rbp` followed by the return address into the caller. The addresses are named with
+//! the symbol table `ksyms` embeds into the kernel image.
+//!
+//! Only frame pointers inside the kernel stack are followed, whose top `init` records at boot, so
+//! a corrupted chain ends the backtrace instead of faulting on an unmapped address.
+
+use core::arch::asm;
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::ksyms::{self, Symbol};
+
+/// Frames followed at most, in case the chain is corrupted into a loop
+pub const MAX_FRAMES: usize = 16;
+
+/// Frame pointer of `_start`, above which no frame of the kernel lies, 0 until `init`
+static STACK_TOP: AtomicU64 = AtomicU64::new(0);
+
+/// Records the top of the kernel stack, to be called first thing in `_start`
+///
+/// Inlined so the frame pointer read is the one of `_start` itself.
+#[inline(always)]
+pub fn init() {
+    STACK_TOP.store(frame_pointer(), Ordering::Relaxed);
+}
+
+/// `rbp` of the function this is inlined into
+#[inline(always)]
+fn frame_pointer() -> u64 {
+    let rbp: u64;
+    unsafe { asm!("mov {}, rbp", out(reg) rbp, options(nomem, nostack, preserves_flags)) };
+    rbp
+}
+
+/// `rbp` of the code an exception interrupted, to be called from its `x86-interrupt` handler
+///
+/// Inlined into the handler, whose prologue pushes the interrupted `rbp` first and points its own
+/// `rbp` there.
+#[inline(always)]
+pub fn interrupted_frame_pointer() -> u64 {
+    unsafe { (frame_pointer() as *const u64).read() }
+}
+
+/// One caller on the stack
+#[derive(Debug, Clone, Copy)]
+pub struct Frame {
+    pub return_address: u64,
+    /// The function the call was made from, if the symbol table has it
+    pub symbol: Option<Symbol>,
+}
+
+impl Frame {
+    fn new(return_address: u64) -> Frame {
+        // The return address can be past the end of the caller if the callee never returns, the
+        // call instruction itself is always inside it
+        let symbol = ksyms::lookup(return_address - 1).map(|symbol| Symbol {
+            offset: symbol.offset + 1,
+            ..symbol
+        });
+        Frame {
+            return_address,
+            symbol,
+        }
+    }
+
+    /// The frame of the instruction at `address` itself, like the one an exception hit
+    pub fn at(address: u64) -> Frame {
+        Frame {
+            return_address: address,
+            symbol: ksyms::lookup(address),
+        }
+    }
+
+    /// Writes the frame as `0x... name+0x...` on a single line of at most `width` characters,
+    /// cutting the beginning of the name if needed since its end is the function itself
+    pub fn write(&self, out: &mut dyn fmt::Write, width: usize) -> fmt::Result {
+        write!(out, "{:#018x}", self.return_address)?;
+        let symbol = match self.symbol {
+            Some(symbol) => symbol,
+            None => return Ok(()),
+        };
+        // The address, a space and `+0x` with the offset digits are always printed
+        let offset_digits = (64 - symbol.offset.leading_zeros() as usize)
+            .div_ceil(4)
+            .max(1);
+        let room = width.saturating_sub(19 + 3 + offset_digits);
+        write!(out, " {}+{:#x}", tail(symbol.name, room), symbol.offset)
+    }
+}
+
+/// Calls `f` with each frame, from the one `rbp` points to outwards
+///
+/// The walk stops at a misaligned frame pointer, one that doesn't move towards the bottom of the
+/// stack or one outside of the stack between here and `_start`, which is how the chain ends at
+/// `_start` or gets lost in corrupted memory. Nothing is walked before `init`.
+pub fn walk<F: FnMut(Frame)>(rbp: u64, mut f: F) {
+    let stack_top = STACK_TOP.load(Ordering::Relaxed);
+    let stack_pointer: u64;
+    unsafe { asm!("mov {}, rsp", out(reg) stack_pointer, options(nomem, nostack, preserves_flags)) };
+    let mut frame = rbp;
+    for _ in 0..MAX_FRAMES {
+        // The frame pointer and the return address after it must both lie in the stack
+        let in_stack = frame >= stack_pointer && frame.saturating_add(16) <= stack_top;
+        if !in_stack || !frame.is_multiple_of(8) {
+            return;
+        }
+        let frame_pointer = frame as *const u64;
+        let (caller_frame, return_address) =
+            unsafe { (frame_pointer.read(), frame_pointer.add(1).read()) };
+        if return_address == 0 {
+            return;
+        }
+        f(Frame::new(return_address));
+        // The stack grows down, so the callers' frames are at higher addresses
+        if caller_frame <= frame {
+            return;
+        }
+        frame = caller_frame;
+    }
+}
+
+/// Prints the first `max_frames` frames from `rbp` outwards, one per line of at most `width`
+/// characters
+///
+/// For exception handlers, `rbp` is the frame pointer of the interrupted code, see
+/// `interrupted_frame_pointer`.
+pub fn print_from(
+    out: &mut dyn fmt::Write,
+    rbp: u64,
+    max_frames: usize,
+    width: usize,
+) -> fmt::Result {
+    let mut result = Ok(());
+    let mut index = 0;
+    walk(rbp, |frame| {
+        if result.is_ok() && index < max_frames {
+            // `  #N ` takes 6 characters
+            result = write!(out, "  #{:<2} ", index)
+                .and_then(|_| frame.write(out, width.saturating_sub(6)))
+                .and_then(|_| writeln!(out));
+        }
+        index += 1;
+    });
+    result
+}
+
+/// Prints the backtrace of the caller, one frame per line
+#[inline(always)]
+pub fn backtrace(out: &mut dyn fmt::Write) -> fmt::Result {
+    print_from(out, frame_pointer(), MAX_FRAMES, usize::MAX)
+}
+
+/// The end of `name` that fits in `width` characters, starting with `..` if it was cut
+fn tail(name: &str, width: usize) -> Tail<'_> {
+    if name.chars().count() <= width {
+        return Tail { cut: false, name };
+    }
+    // The last `keep` characters, after the `..`
+    let keep = width.saturating_sub(2);
+    let start = match keep.checked_sub(1) {
+        Some(last) => name
+            .char_indices()
+            .rev()
+            .nth(last)
+            .map_or(0, |(index, _)| index),
+        None => name.len(),
+    };
+    Tail {
+        cut: true,
+        name: &name[start..],
+    }
+}
+
+struct Tail<'a> {
+    cut: bool,
+    name: &'a str,
+}
+
+impl fmt::Display for Tail<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.cut {
+            f.write_str("..")?;
+        }
+        f.write_str(self.name)
+    }
+}
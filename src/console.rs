@@ -0,0 +1,155 @@
+//! The places kernel output goes to, and the registry `print!` fans out through.
+//!
+//! Every output device implements `Console` and is registered once at boot with the minimum
+//! level of the log messages it wants. `print!` output has no level and reaches every enabled
+//! sink, log messages only the sinks whose level lets them through, prefixed with the time since
+//! boot. Sinks can be turned off and back on at runtime by name. Whatever the sinks, every message
+//! is also kept in `ring_buffer`.
+
+use core::fmt;
+use log::{Level, LevelFilter};
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+
+use crate::time;
+
+pub mod ring_buffer;
+
+/// An output device for kernel messages
+///
+/// Sinks are shared between all CPUs and interrupt handlers, so they take `&self` and do their
+/// own locking.
+pub trait Console: Sync {
+    /// Short unique name, used to configure the sink
+    fn name(&self) -> &'static str;
+
+    fn write_str(&self, s: &str);
+}
+
+/// How many sinks can be registered
+const MAX_SINKS: usize = 8;
+
+#[derive(Clone, Copy)]
+struct Sink {
+    console: &'static dyn Console,
+    enabled: bool,
+    level: LevelFilter,
+}
+
+static SINKS: Mutex<[Option<Sink>; MAX_SINKS]> = Mutex::new([None; MAX_SINKS]);
+
+/// Why a sink couldn't be registered
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegisterError {
+    /// There already is a sink with this name
+    DuplicateName,
+    /// All `MAX_SINKS` slots are taken
+    Full,
+}
+
+/// Adds `console` as an enabled sink receiving the log messages from `level` on
+pub fn register(console: &'static dyn Console, level: LevelFilter) -> Result<(), RegisterError> {
+    interrupts::without_interrupts(|| {
+        let mut sinks = SINKS.lock();
+        if sinks.iter().flatten().any(|sink| sink.console.name() == console.name()) {
+            return Err(RegisterError::DuplicateName);
+        }
+        let slot = sinks.iter_mut().find(|slot| slot.is_none()).ok_or(RegisterError::Full)?;
+        *slot = Some(Sink {
+            console,
+            enabled: true,
+            level,
+        });
+        log::set_max_level(max_level_of(&*sinks));
+        Ok(())
+    })
+}
+
+/// Turns the sink called `name` on or off, returns `false` if there is none
+#[allow(dead_code)]
+pub fn set_enabled(name: &str, enabled: bool) -> bool {
+    update(name, |sink| sink.enabled = enabled)
+}
+
+/// Changes the minimum level of the log messages the sink called `name` receives
+#[allow(dead_code)]
+pub fn set_level(name: &str, level: LevelFilter) -> bool {
+    update(name, |sink| sink.level = level)
+}
+
+/// The most verbose level any enabled sink wants, log messages above it can be skipped early
+pub fn max_level() -> LevelFilter {
+    interrupts::without_interrupts(|| max_level_of(&*SINKS.lock()))
+}
+
+/// The `log` crate skips the messages above its maximum level, which follows the sinks
+fn max_level_of(sinks: &[Option<Sink>]) -> LevelFilter {
+    let enabled = sinks.iter().flatten().filter(|sink| sink.enabled);
+    enabled.map(|sink| sink.level).max().unwrap_or(LevelFilter::Off)
+}
+
+fn update<F: FnOnce(&mut Sink)>(name: &str, f: F) -> bool {
+    interrupts::without_interrupts(|| {
+        let mut sinks = SINKS.lock();
+        match sinks.iter_mut().flatten().find(|sink| sink.console.name() == name) {
+            Some(sink) => f(sink),
+            None => return false,
+        }
+        log::set_max_level(max_level_of(&*sinks));
+        true
+    })
+}
+
+/// Records `args` then writes it to every enabled sink accepting `level`, `None` being `print!`
+/// output
+///
+/// Log messages are shown as `[    1.204518] message`, with the time they are recorded at.
+///
+/// Interrupts are disabled meanwhile, so an interrupt handler printing can't wait forever for a
+/// lock held by the code it interrupted.
+pub fn write(level: Option<Level>, args: fmt::Arguments) {
+    let timestamp = time::now();
+    ring_buffer::record(level, timestamp, args);
+    interrupts::without_interrupts(|| {
+        let sinks = SINKS.lock();
+        for sink in sinks.iter().flatten().filter(|sink| sink.enabled) {
+            if level.is_none_or(|level| level <= sink.level) {
+                let mut out = SinkWriter(sink.console);
+                // The sinks never fail
+                let _ = match level {
+                    Some(_) => fmt::write(&mut out, format_args!("[{}] {}", timestamp, args)),
+                    None => fmt::write(&mut out, args),
+                };
+            }
+        }
+    });
+}
+
+/// Like the `print!` macro in the standard library, but prints to every enabled console sink.
+#[macro_export]
+macro_rules! print {
+    ($($arg:tt)*) => ($crate::console::_print(format_args!($($arg)*)));
+}
+
+/// Like the `println!` macro in the standard library, but prints to every enabled console sink.
+#[macro_export]
+macro_rules! println {
+    () => ($crate::print!("\n"));
+    ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
+}
+
+/// Prints the given formatted string to the registered sinks.
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    write(None, args);
+}
+
+/// Lets `fmt::write` print to a sink
+struct SinkWriter(&'static dyn Console);
+
+impl fmt::Write for SinkWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.0.write_str(s);
+        Ok(())
+    }
+}
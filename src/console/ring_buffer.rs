@@ -0,0 +1,217 @@
+//! Every message written through the consoles, kept in memory to replay it later (`dmesg`, over
+//! the serial port, in a crash dump) after it scrolled off the screen.
+//!
+//! Each `print!` or log call becomes one record with a sequence number, the time since boot it was
+//! written at and its level. The buffer is lock-free so interrupt handlers and the panic handler
+//! can record and replay at any time: writers claim a slot with its sequence number, and readers
+//! copy a slot then check its state didn't change meanwhile, retrying or skipping it otherwise.
+
+use core::fmt;
+use core::str;
+use core::sync::atomic::{self, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use log::{Level, LevelFilter};
+
+use crate::time::Timestamp;
+
+/// Records kept, the oldest ones are overwritten once they are all used
+pub const RECORD_COUNT: usize = 128;
+
+/// Bytes of text in a record, longer messages are cut
+pub const RECORD_SIZE: usize = 192;
+
+/// Times a reader tries to copy a slot that keeps changing before it skips it
+const READ_RETRIES: usize = 4;
+
+/// Stored level of `print!` output, which has none
+const NO_LEVEL: u8 = 0;
+
+struct Slot {
+    /// 0 while empty, `2 * sequence + 1` while record `sequence` is written, `2 * sequence + 2`
+    /// once it is complete
+    state: AtomicU64,
+    /// `Timestamp::as_micros`
+    timestamp: AtomicU64,
+    level: AtomicU8,
+    len: AtomicUsize,
+    text: [AtomicU8; RECORD_SIZE],
+}
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_BYTE: AtomicU8 = AtomicU8::new(0);
+
+#[allow(clippy::declare_interior_mutable_const)]
+const EMPTY_SLOT: Slot = Slot {
+    state: AtomicU64::new(0),
+    timestamp: AtomicU64::new(0),
+    level: AtomicU8::new(NO_LEVEL),
+    len: AtomicUsize::new(0),
+    text: [EMPTY_BYTE; RECORD_SIZE],
+};
+
+static SLOTS: [Slot; RECORD_COUNT] = [EMPTY_SLOT; RECORD_COUNT];
+
+/// Sequence number of the next record
+static NEXT_SEQUENCE: AtomicU64 = AtomicU64::new(0);
+
+/// Records lost because their slot was still being written when they wrapped around to it
+static DROPPED: AtomicU64 = AtomicU64::new(0);
+
+/// A copy of one message
+pub struct Record {
+    pub sequence: u64,
+    /// When the message was written
+    pub timestamp: Timestamp,
+    /// `None` for `print!` output
+    pub level: Option<Level>,
+    len: usize,
+    text: [u8; RECORD_SIZE],
+}
+
+impl Record {
+    pub fn text(&self) -> &str {
+        // Writers only cut messages at character boundaries
+        str::from_utf8(&self.text[..self.len]).unwrap_or("")
+    }
+}
+
+/// Stores `args`, written at `timestamp`, as the next record
+pub fn record(level: Option<Level>, timestamp: Timestamp, args: fmt::Arguments) {
+    let sequence = NEXT_SEQUENCE.fetch_add(1, Ordering::Relaxed);
+    let slot = &SLOTS[slot_index(sequence)];
+    let state = slot.state.load(Ordering::Relaxed);
+    // A writer still busy with the slot, or a newer one already done with it, keeps it
+    if state % 2 == 1
+        || state > 2 * sequence
+        || slot
+            .state
+            .compare_exchange(state, 2 * sequence + 1, Ordering::Acquire, Ordering::Relaxed)
+            .is_err()
+    {
+        DROPPED.fetch_add(1, Ordering::Relaxed);
+        return;
+    }
+    atomic::fence(Ordering::Release);
+
+    slot.timestamp.store(timestamp.as_micros(), Ordering::Relaxed);
+    slot.level.store(level.map_or(NO_LEVEL, |level| level as u8), Ordering::Relaxed);
+    let mut writer = SlotWriter {
+        slot,
+        len: 0,
+        full: false,
+    };
+    let _ = fmt::write(&mut writer, args);
+    slot.len.store(writer.len, Ordering::Relaxed);
+
+    slot.state.store(2 * sequence + 2, Ordering::Release);
+}
+
+/// The oldest record still kept whose sequence number is at least `sequence`
+pub fn read(sequence: u64) -> Option<Record> {
+    let next = NEXT_SEQUENCE.load(Ordering::Relaxed);
+    let oldest = next.saturating_sub(RECORD_COUNT as u64);
+    (sequence.max(oldest)..next).find_map(read_slot)
+}
+
+/// Writes the records from `sequence` on to `out`, leaving out the log messages above `level`
+///
+/// Log messages and the `print!` output starting a line get their timestamp in front, like on the
+/// consoles. Returns the sequence number to continue from next time, so only new messages get
+/// replayed.
+pub fn replay(out: &mut dyn fmt::Write, mut sequence: u64, level: LevelFilter) -> u64 {
+    let mut line_start = true;
+    while let Some(record) = read(sequence) {
+        sequence = record.sequence + 1;
+        if record.level.is_none_or(|record_level| record_level <= level) {
+            let text = record.text();
+            if record.level.is_some() || line_start {
+                let _ = write!(out, "[{}] ", record.timestamp);
+            }
+            let _ = out.write_str(text);
+            line_start = text.ends_with('\n');
+        }
+    }
+    sequence
+}
+
+/// Writes every message kept to `out`, like `dmesg`
+pub fn dmesg(out: &mut dyn fmt::Write) {
+    let dropped = DROPPED.load(Ordering::Relaxed);
+    if dropped > 0 {
+        let _ = writeln!(out, "[{} messages dropped]", dropped);
+    }
+    replay(out, 0, LevelFilter::Trace);
+}
+
+fn slot_index(sequence: u64) -> usize {
+    (sequence % RECORD_COUNT as u64) as usize
+}
+
+/// Copies record `sequence` out of its slot, if it is complete and wasn't overwritten
+fn read_slot(sequence: u64) -> Option<Record> {
+    let slot = &SLOTS[slot_index(sequence)];
+    let complete = 2 * sequence + 2;
+    for _ in 0..READ_RETRIES {
+        let state = slot.state.load(Ordering::Acquire);
+        if state > complete {
+            return None;
+        }
+        let mut record = Record {
+            sequence,
+            timestamp: Timestamp::from_micros(slot.timestamp.load(Ordering::Relaxed)),
+            level: level_from_u8(slot.level.load(Ordering::Relaxed)),
+            len: slot.len.load(Ordering::Relaxed).min(RECORD_SIZE),
+            text: [0; RECORD_SIZE],
+        };
+        for (byte, stored) in record.text[..record.len].iter_mut().zip(slot.text.iter()) {
+            *byte = stored.load(Ordering::Relaxed);
+        }
+        atomic::fence(Ordering::Acquire);
+        if state == complete && slot.state.load(Ordering::Relaxed) == state {
+            return Some(record);
+        }
+        if state != complete - 1 {
+            return None;
+        }
+        // Still being written, the writer may be done by the next try
+    }
+    None
+}
+
+fn level_from_u8(level: u8) -> Option<Level> {
+    match level {
+        1 => Some(Level::Error),
+        2 => Some(Level::Warn),
+        3 => Some(Level::Info),
+        4 => Some(Level::Debug),
+        5 => Some(Level::Trace),
+        _ => None,
+    }
+}
+
+/// Lets `fmt::write` fill a slot, cutting the message at the last character that fits
+struct SlotWriter {
+    slot: &'static Slot,
+    len: usize,
+    /// Set once a piece didn't fit, so the following ones don't get appended to a cut message
+    full: bool,
+}
+
+impl fmt::Write for SlotWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        if self.full {
+            return Ok(());
+        }
+        let mut end = s.len().min(RECORD_SIZE - self.len);
+        if end < s.len() {
+            self.full = true;
+            while !s.is_char_boundary(end) {
+                end -= 1;
+            }
+        }
+        for (stored, &byte) in self.slot.text[self.len..].iter().zip(&s.as_bytes()[..end]) {
+            stored.store(byte, Ordering::Relaxed);
+        }
+        self.len += end;
+        Ok(())
+    }
+}
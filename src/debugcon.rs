@@ -0,0 +1,27 @@
+//! QEMU's debug console: every byte written to port 0xE9 ends up in a file or on the terminal
+//! (`-debugcon stdio`), with no setup and no waiting, which makes it handy for early output.
+//!
+//! Without the device the writes are simply ignored.
+
+use x86_64::instructions::port::Port;
+
+use crate::console::Console;
+
+/// The port of the debug console, also used by Bochs
+pub const DEBUGCON_PORT: u16 = 0xe9;
+
+/// Console sink writing to the debug console port
+pub struct DebugconSink;
+
+impl Console for DebugconSink {
+    fn name(&self) -> &'static str {
+        "debugcon"
+    }
+
+    fn write_str(&self, s: &str) {
+        let mut port = Port::new(DEBUGCON_PORT);
+        for byte in s.bytes() {
+            unsafe { port.write(byte) };
+        }
+    }
+}
@@ -0,0 +1,208 @@
+//! Text console drawn with a bitmap font on a graphics mode framebuffer.
+//!
+//! It prints with the same `Color`s and `fmt::Write` as the text mode `Writer`, scrolling once the
+//! bottom is reached, but understands fewer escapes: SGR colors, `CSI 2 J`, `ESC c` and the control
+//! characters `\n`, `\r`, `\t` and `\x08`. While it is enabled with `init`, `print!` output
+//! reaches it through `FramebufferSink`. Build with the `framebuffer` feature to make it the output
+//! at boot.
+
+use core::fmt;
+use spin::Mutex;
+
+use crate::console::Console;
+use crate::psf::{self, Font};
+use crate::vga_buffer::ansi::{Action, CsiSequence, GraphicRendition, Parser};
+use crate::vga_buffer::theme;
+use crate::vga_buffer::{Color, ColorCode, DEFAULT_TAB_WIDTH};
+use crate::vga_graphics::{self, Canvas, Mode};
+
+/// The framebuffer console, `None` while the screen is in text mode
+pub static CONSOLE: Mutex<Option<FramebufferConsole>> = Mutex::new(None);
+
+/// Drawn for characters missing from the font, like 0xfe in text mode
+const REPLACEMENT_CHAR: char = '■';
+
+/// Switches to the graphics `mode` and sets the framebuffer console up on it
+#[allow(dead_code)]
+pub fn init(mode: Mode) {
+    let font = Font::parse(psf::DEFAULT_FONT).expect("the built-in font is a valid PSF file");
+    let canvas = vga_graphics::enter(mode);
+    *CONSOLE.lock() = Some(FramebufferConsole::new(canvas, font));
+}
+
+/// Goes back to text mode, `FramebufferSink` drops the output from then on
+#[allow(dead_code)]
+pub fn disable() {
+    if CONSOLE.lock().take().is_some() {
+        vga_graphics::leave();
+    }
+}
+
+/// Console sink writing to `CONSOLE` while it is enabled, and nowhere otherwise
+pub struct FramebufferSink;
+
+impl Console for FramebufferSink {
+    fn name(&self) -> &'static str {
+        "framebuffer"
+    }
+
+    fn write_str(&self, s: &str) {
+        if let Some(console) = CONSOLE.lock().as_mut() {
+            console.write_string(s);
+        }
+    }
+}
+
+/// A grid of character cells over a `Canvas`, each as large as a glyph of the font
+pub struct FramebufferConsole {
+    canvas: Canvas,
+    font: Font<'static>,
+    columns: usize,
+    rows: usize,
+    column_position: usize,
+    row_position: usize,
+    color_code: ColorCode,
+    default_color_code: ColorCode,
+    rendition: GraphicRendition,
+    parser: Parser,
+}
+
+#[allow(dead_code)]
+impl FramebufferConsole {
+    /// Creates a console covering the whole canvas, and clears it
+    pub fn new(canvas: Canvas, font: Font<'static>) -> FramebufferConsole {
+        let color_code = theme::current().color_code();
+        let mut console = FramebufferConsole {
+            columns: canvas.width() / font.width(),
+            rows: canvas.height() / font.height(),
+            canvas,
+            font,
+            column_position: 0,
+            row_position: 0,
+            color_code,
+            default_color_code: color_code,
+            rendition: GraphicRendition::new(),
+            parser: Parser::new(),
+        };
+        console.clear();
+        console
+    }
+
+    /// Size of the console in characters, as `(columns, rows)`
+    pub fn size(&self) -> (usize, usize) {
+        (self.columns, self.rows)
+    }
+
+    /// Sets the colors of the following output, and the ones `CSI 0 m` goes back to
+    pub fn set_color(&mut self, foreground: Color, background: Color) {
+        self.color_code = ColorCode::new(foreground, background);
+        self.default_color_code = self.color_code;
+    }
+
+    /// Fills the screen with the background color and moves to the top left corner
+    pub fn clear(&mut self) {
+        self.canvas.clear(self.color_code.background());
+        self.row_position = 0;
+        self.column_position = 0;
+    }
+
+    /// Writes the given string
+    ///
+    /// SGR colors, `CSI 2 J` and `ESC c` are interpreted, as well as `\n`, `\r`, `\t` and `\x08`.
+    /// Other sequences and control characters, like cursor movement or `\x07`, are dropped.
+    pub fn write_string(&mut self, s: &str) {
+        for c in s.chars() {
+            match self.parser.advance(c) {
+                Some(Action::Print(c)) => self.write_char(c),
+                Some(Action::Csi(sequence)) => self.execute_csi(&sequence),
+                Some(Action::Reset) => {
+                    self.color_code = self.default_color_code;
+                    self.rendition = GraphicRendition::new();
+                    self.clear();
+                }
+                // There is no cursor to save
+                Some(Action::SaveCursor) | Some(Action::RestoreCursor) | None => {}
+            }
+        }
+    }
+
+    fn write_char(&mut self, c: char) {
+        match c {
+            '\n' => self.new_line(),
+            '\r' => self.column_position = 0,
+            '\t' => {
+                let next_stop = (self.column_position / DEFAULT_TAB_WIDTH + 1) * DEFAULT_TAB_WIDTH;
+                self.column_position = next_stop.min(self.columns);
+            }
+            '\x08' => {
+                if self.column_position > 0 {
+                    self.column_position = self.column_position.min(self.columns) - 1;
+                    self.draw_glyph(' ');
+                }
+            }
+            c if c.is_control() => {}
+            c => {
+                if self.column_position >= self.columns {
+                    self.new_line();
+                }
+                self.draw_glyph(c);
+                self.column_position += 1;
+            }
+        }
+    }
+
+    /// Draws `c` in the cell at the current position
+    fn draw_glyph(&mut self, c: char) {
+        let font = self.font;
+        let glyph = font
+            .glyph_index(c)
+            .or_else(|| font.glyph_index(REPLACEMENT_CHAR))
+            .and_then(|index| font.glyph(index));
+        let glyph = match glyph {
+            Some(glyph) => glyph,
+            None => return,
+        };
+        let x = (self.column_position * font.width()) as i32;
+        let y = (self.row_position * font.height()) as i32;
+        let (foreground, background) = (self.color_code.foreground(), self.color_code.background());
+        for (line, bytes) in glyph.chunks(font.bytes_per_row()).enumerate() {
+            for (byte, &bits) in bytes.iter().enumerate() {
+                self.canvas.draw_bits(x + 8 * byte as i32, y + line as i32, bits, foreground, background);
+            }
+        }
+    }
+
+    fn new_line(&mut self) {
+        self.column_position = 0;
+        if self.row_position + 1 < self.rows {
+            self.row_position += 1;
+        } else {
+            self.canvas.scroll_up(self.font.height(), self.color_code.background());
+        }
+    }
+
+    /// Only colors (`m`) and clearing the screen (`2J`) are supported, there is no cursor to move
+    fn execute_csi(&mut self, sequence: &CsiSequence) {
+        if sequence.private {
+            return;
+        }
+        match sequence.final_byte {
+            b'm' => self.select_graphic_rendition(sequence.params()),
+            b'J' if sequence.param(0, 0) == 2 => self.clear(),
+            _ => {}
+        }
+    }
+
+    fn select_graphic_rendition(&mut self, params: &[u16]) {
+        self.color_code = self
+            .rendition
+            .select(params, self.color_code, self.default_color_code);
+    }
+}
+
+impl fmt::Write for FramebufferConsole {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.write_string(s);
+        Ok(())
+    }
+}
@@ -0,0 +1,51 @@
+//! Global descriptor table with the task state segment (TSS), for a double fault stack.
+//!
+//! A double fault raised by a stack overflow can't push its interrupt frame on the overflowed
+//! stack, which would turn it into a triple fault and reset the machine. The TSS gives it an
+//! interrupt stack table entry of its own, `DOUBLE_FAULT_IST_INDEX`.
+
+use lazy_static::lazy_static;
+use x86_64::instructions::segmentation::{Segment, CS};
+use x86_64::instructions::tables::load_tss;
+use x86_64::structures::gdt::{Descriptor, GlobalDescriptorTable, SegmentSelector};
+use x86_64::structures::tss::TaskStateSegment;
+use x86_64::VirtAddr;
+
+/// Interrupt stack table entry the double fault handler runs on
+pub const DOUBLE_FAULT_IST_INDEX: u16 = 0;
+
+/// Enough for the panic report, which formats and walks the backtrace on it
+const DOUBLE_FAULT_STACK_SIZE: usize = 5 * 4096;
+
+lazy_static! {
+    static ref TSS: TaskStateSegment = {
+        static mut STACK: [u8; DOUBLE_FAULT_STACK_SIZE] = [0; DOUBLE_FAULT_STACK_SIZE];
+        let mut tss = TaskStateSegment::new();
+        // The stack grows down from its end
+        let stack_start = VirtAddr::from_ptr(core::ptr::addr_of!(STACK));
+        tss.interrupt_stack_table[DOUBLE_FAULT_IST_INDEX as usize] =
+            stack_start + DOUBLE_FAULT_STACK_SIZE;
+        tss
+    };
+
+    static ref GDT: (GlobalDescriptorTable, Selectors) = {
+        let mut gdt = GlobalDescriptorTable::new();
+        let code = gdt.add_entry(Descriptor::kernel_code_segment());
+        let tss = gdt.add_entry(Descriptor::tss_segment(&TSS));
+        (gdt, Selectors { code, tss })
+    };
+}
+
+struct Selectors {
+    code: SegmentSelector,
+    tss: SegmentSelector,
+}
+
+/// Loads the GDT and the TSS, to be called before the IDT refers to `DOUBLE_FAULT_IST_INDEX`
+pub fn init() {
+    GDT.0.load();
+    unsafe {
+        CS::set_reg(GDT.1.code);
+        load_tss(GDT.1.tss);
+    }
+}
@@ -0,0 +1,175 @@
+//! Interrupt descriptor table, the two 8259 PICs and the PIT timer interrupt.
+//!
+//! Page faults, general protection faults and double faults stop the kernel with the panic screen,
+//! a breakpoint prints where it was hit and carries on. Only the timer interrupt (IRQ 0) is
+//! unmasked, at `TIMER_FREQUENCY` Hz. Each tick goes to the status bar, which counts it and
+//! redraws itself now and then, and to the PC speaker bell.
+
+use core::fmt;
+use lazy_static::lazy_static;
+use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
+use x86_64::structures::idt::{InterruptDescriptorTable, InterruptStackFrame, PageFaultErrorCode};
+
+use crate::backtrace;
+use crate::gdt;
+use crate::panic_screen::{self, Cause, Registers};
+use crate::pc_speaker;
+use crate::pit;
+use crate::print;
+use crate::status_bar;
+
+/// Timer interrupts per second
+pub const TIMER_FREQUENCY: u32 = 100;
+
+/// The PIC IRQs are moved past the 32 vectors reserved for CPU exceptions
+const PIC_1_OFFSET: u8 = 32;
+const PIC_2_OFFSET: u8 = PIC_1_OFFSET + 8;
+
+const PIC_1_COMMAND: u16 = 0x20;
+const PIC_1_DATA: u16 = 0x21;
+const PIC_2_COMMAND: u16 = 0xa0;
+const PIC_2_DATA: u16 = 0xa1;
+
+/// ICW1: initialization, a fourth word follows
+const ICW1_INIT: u8 = 0x11;
+/// ICW4: 8086 mode
+const ICW4_8086: u8 = 0x01;
+const END_OF_INTERRUPT: u8 = 0x20;
+
+/// Every IRQ but the timer masked on the first PIC, all of them on the second
+const PIC_1_MASK: u8 = !(1 << 0);
+const PIC_2_MASK: u8 = 0xff;
+
+const TIMER_VECTOR: u8 = PIC_1_OFFSET;
+/// A masked IRQ can still show up as IRQ 7 when its line drops before the CPU acknowledges it
+const SPURIOUS_VECTOR: u8 = PIC_1_OFFSET + 7;
+
+lazy_static! {
+    static ref IDT: InterruptDescriptorTable = {
+        let mut idt = InterruptDescriptorTable::new();
+        idt.breakpoint.set_handler_fn(breakpoint_handler);
+        idt.page_fault.set_handler_fn(page_fault_handler);
+        idt.general_protection_fault.set_handler_fn(general_protection_fault_handler);
+        unsafe {
+            idt.double_fault
+                .set_handler_fn(double_fault_handler)
+                .set_stack_index(gdt::DOUBLE_FAULT_IST_INDEX);
+        }
+        idt[TIMER_VECTOR as usize].set_handler_fn(timer_handler);
+        idt[SPURIOUS_VECTOR as usize].set_handler_fn(spurious_handler);
+        idt
+    };
+}
+
+/// Loads the IDT, CPU exceptions are handled from then on
+///
+/// `gdt::init` has to run first, for the double fault stack.
+pub fn init_idt() {
+    IDT.load();
+}
+
+/// Starts the timer and enables interrupts, after `init_idt`
+pub fn init() {
+    remap_pics();
+    start_timer(TIMER_FREQUENCY);
+    interrupts::enable();
+}
+
+/// Moves the IRQs to `PIC_1_OFFSET` onwards and applies the masks
+fn remap_pics() {
+    let mut command_1: Port<u8> = Port::new(PIC_1_COMMAND);
+    let mut data_1: Port<u8> = Port::new(PIC_1_DATA);
+    let mut command_2: Port<u8> = Port::new(PIC_2_COMMAND);
+    let mut data_2: Port<u8> = Port::new(PIC_2_DATA);
+    unsafe {
+        command_1.write(ICW1_INIT);
+        command_2.write(ICW1_INIT);
+        data_1.write(PIC_1_OFFSET);
+        data_2.write(PIC_2_OFFSET);
+        // ICW3: the second PIC is cascaded on IRQ 2 of the first one
+        data_1.write(1 << 2);
+        data_2.write(2);
+        data_1.write(ICW4_8086);
+        data_2.write(ICW4_8086);
+        data_1.write(PIC_1_MASK);
+        data_2.write(PIC_2_MASK);
+    }
+}
+
+/// Makes channel 0 of the PIT fire IRQ 0 `frequency` times per second
+fn start_timer(frequency: u32) {
+    pit::start(pit::CHANNEL_0, pit::CHANNEL_0_RATE_GENERATOR, pit::divisor(frequency));
+}
+
+extern "x86-interrupt" fn timer_handler(_frame: InterruptStackFrame) {
+    status_bar::tick();
+    pc_speaker::tick();
+    unsafe { Port::new(PIC_1_COMMAND).write(END_OF_INTERRUPT) };
+}
+
+/// Counted, but not acknowledged since the PIC didn't set the IRQ in service
+extern "x86-interrupt" fn spurious_handler(_frame: InterruptStackFrame) {
+    status_bar::count_interrupt();
+}
+
+/// Prints the address of the `int3` and the backtrace from there, then returns to it
+extern "x86-interrupt" fn breakpoint_handler(frame: InterruptStackFrame) {
+    print!("Breakpoint at {:#x}, backtrace:\n", frame.instruction_pointer.as_u64());
+    // Without an error code, the handler's frame returns right into the interrupted code
+    let _ = backtrace::backtrace(&mut Print);
+}
+
+/// The faulting address is in CR2, shown with the registers
+extern "x86-interrupt" fn page_fault_handler(
+    frame: InterruptStackFrame,
+    error_code: PageFaultErrorCode,
+) {
+    let registers = Registers::at_exception(&frame, backtrace::interrupted_frame_pointer());
+    panic_screen::show(
+        Cause::Exception {
+            name: "page fault",
+            error_code: Some(error_code.bits()),
+            frame: &frame,
+        },
+        &registers,
+    )
+}
+
+extern "x86-interrupt" fn general_protection_fault_handler(
+    frame: InterruptStackFrame,
+    error_code: u64,
+) {
+    let registers = Registers::at_exception(&frame, backtrace::interrupted_frame_pointer());
+    panic_screen::show(
+        Cause::Exception {
+            name: "general protection fault",
+            error_code: Some(error_code),
+            frame: &frame,
+        },
+        &registers,
+    )
+}
+
+/// Runs on its own stack, the error code is always 0
+extern "x86-interrupt" fn double_fault_handler(frame: InterruptStackFrame, _error_code: u64) -> ! {
+    let registers = Registers::at_exception(&frame, backtrace::interrupted_frame_pointer());
+    panic_screen::show(
+        Cause::Exception {
+            name: "double fault",
+            error_code: None,
+            frame: &frame,
+        },
+        &registers,
+    )
+}
+
+/// Lets the backtrace go to `print!`
+struct Print;
+
+impl fmt::Write for Print {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        print!("{}", s);
+        Ok(())
+    }
+}
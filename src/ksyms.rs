@@ -0,0 +1,108 @@
+//! Symbol table embedded in the kernel image, to put function names on backtrace addresses.
+//!
+//! `KSYMS` reserves the `.ksyms` section, which `tools/ksyms.py` fills after linking with the
+//! addresses, sizes and demangled names of the functions in the ELF symbol table (`cargo run`
+//! does it through `tools/runner.sh`):
+//!
+//! ```text
+//! "KSYM"  u32 count
+//! count × (u64 address, u32 size, u32 name offset, u32 name length), sorted by address
+//! names, UTF-8, their offsets counted from the start of the section
+//! ```
+//!
+//! All integers are little endian, a size of 0 means the symbol has none. A kernel that wasn't
+//! patched has an empty table and its backtraces only show addresses.
+
+use core::convert::TryInto;
+use core::hint;
+use core::str;
+
+/// Bytes reserved for the table, `tools/ksyms.py` fails if the symbols don't fit
+pub const KSYMS_SIZE: usize = 128 * 1024;
+
+const MAGIC: [u8; 4] = *b"KSYM";
+const HEADER_SIZE: usize = 8;
+const ENTRY_SIZE: usize = 20;
+
+/// Starts with the magic so the section isn't all zeroes, which could turn it into `.bss`
+#[used]
+#[link_section = ".ksyms"]
+static KSYMS: [u8; KSYMS_SIZE] = empty_table();
+
+const fn empty_table() -> [u8; KSYMS_SIZE] {
+    let mut table = [0; KSYMS_SIZE];
+    table[0] = MAGIC[0];
+    table[1] = MAGIC[1];
+    table[2] = MAGIC[2];
+    table[3] = MAGIC[3];
+    table
+}
+
+/// The function an address belongs to
+#[derive(Debug, Clone, Copy)]
+pub struct Symbol {
+    pub name: &'static str,
+    /// Distance from the start of the function
+    pub offset: u64,
+}
+
+/// Finds the function containing `address`, that is the last one starting at or before it
+///
+/// Addresses past the end of that function, in code the table doesn't cover, have no symbol. A
+/// function without a size is taken to reach up to the next one.
+pub fn lookup(address: u64) -> Option<Symbol> {
+    let table = table();
+    if table[..4] != MAGIC {
+        return None;
+    }
+    let count = read_u32(table, 4) as usize;
+    let count = count.min((KSYMS_SIZE - HEADER_SIZE) / ENTRY_SIZE);
+    // Index of the first entry after `address`
+    let after = partition_point(count, |index| entry_address(table, index) <= address);
+    let index = after.checked_sub(1)?;
+    let entry = HEADER_SIZE + index * ENTRY_SIZE;
+    let offset = address - entry_address(table, index);
+    let size = u64::from(read_u32(table, entry + 8));
+    if size != 0 && offset >= size {
+        return None;
+    }
+    let start = read_u32(table, entry + 12) as usize;
+    let len = read_u32(table, entry + 16) as usize;
+    let name = table.get(start..start.checked_add(len)?)?;
+    Some(Symbol {
+        name: str::from_utf8(name).ok()?,
+        offset,
+    })
+}
+
+/// The table as patched into the image
+///
+/// The compiler knows the initial contents of `KSYMS` and would fold the reads into the empty
+/// table, the pointer is passed through `black_box` to make it read the actual bytes.
+fn table() -> &'static [u8] {
+    let table: *const [u8; KSYMS_SIZE] = hint::black_box(&KSYMS);
+    unsafe { &*table }
+}
+
+fn entry_address(table: &[u8], index: usize) -> u64 {
+    let offset = HEADER_SIZE + index * ENTRY_SIZE;
+    u64::from_le_bytes(table[offset..offset + 8].try_into().unwrap())
+}
+
+fn read_u32(table: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(table[offset..offset + 4].try_into().unwrap())
+}
+
+/// Number of leading entries for which `before` holds, as with `slice::partition_point`
+fn partition_point<F: Fn(usize) -> bool>(count: usize, before: F) -> usize {
+    let (mut low, mut high) = (0, count);
+    while low < high {
+        let middle = low + (high - low) / 2;
+        if before(middle) {
+            low = middle + 1;
+        } else {
+            high = middle;
+        }
+    }
+    low
+}
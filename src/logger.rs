@@ -0,0 +1,61 @@
+//! Backend of the `log` crate: `error!`, `warn!`, `info!`, `debug!` and `trace!` go to the
+//! console sinks that accept their level.
+//!
+//! Each message gets a line of its own with its level colored with SGR escapes and the module it
+//! comes from, after the seconds since boot that `console::write` puts in front of it:
+//!
+//! ```text
+//! [    1.204518] WARN  basic_os::status_bar: no timer yet
+//! ```
+
+use log::{Level, Log, Metadata, Record};
+
+use crate::console;
+use crate::vga_buffer::ansi::foreground_sgr;
+use crate::vga_buffer::Color;
+
+static LOGGER: KernelLogger = KernelLogger;
+
+/// Installs the kernel logger, the sinks should be registered first so their levels apply
+pub fn init() {
+    // Fails only if a logger is already set, in which case that one stays
+    let _ = log::set_logger(&LOGGER);
+    log::set_max_level(console::max_level());
+}
+
+/// Color of the level tag
+fn level_color(level: Level) -> Color {
+    match level {
+        Level::Error => Color::LightRed,
+        Level::Warn => Color::Yellow,
+        Level::Info => Color::LightGreen,
+        Level::Debug => Color::LightCyan,
+        Level::Trace => Color::DarkGray,
+    }
+}
+
+struct KernelLogger;
+
+impl Log for KernelLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        console::write(
+            Some(record.level()),
+            format_args!(
+                "\x1b[{}m{:<5}\x1b[0m {}: {}\n",
+                foreground_sgr(level_color(record.level())),
+                record.level(),
+                record.target(),
+                record.args(),
+            ),
+        );
+    }
+
+    fn flush(&self) {}
+}
@@ -1,21 +1,79 @@
 #![no_std]
 #![no_main]
+#![feature(abi_x86_interrupt)]
 
 use core::panic::PanicInfo;
+use log::LevelFilter;
 
+mod backtrace;
+mod console;
+mod debugcon;
+mod fb_console;
+mod gdt;
+mod interrupts;
+mod ksyms;
+mod logger;
+mod panic_screen;
+mod pc_speaker;
+mod pit;
+mod psf;
+#[cfg(feature = "qemu-exit")]
+mod qemu;
+#[cfg(feature = "screen-tests")]
+mod screen_tests;
+mod serial;
+mod status_bar;
+mod time;
+mod tui;
 mod vga_buffer;
+mod vga_graphics;
+mod vga_registers;
+
+/// Colors of the consoles, applied first thing at boot
+const THEME: &vga_buffer::theme::Theme = &vga_buffer::theme::VGA;
+
+/// Where `print!` output goes at boot, with the minimum level of the log messages each sink gets
+const SINKS: [(&dyn console::Console, LevelFilter); 3] = [
+    (&vga_buffer::VgaSink, LevelFilter::Info),
+    (&fb_console::FramebufferSink, LevelFilter::Info),
+    (&debugcon::DebugconSink, LevelFilter::Trace),
+];
 
 #[no_mangle]
 pub extern "C" fn _start() -> ! {
-    use core::fmt::Write;
-    vga_buffer::WRITER.lock().write_str("Hello again").unwrap();
-    write!(vga_buffer::WRITER.lock(), ", some numbers: {} {}", 42, 1.337).unwrap();
-    loop {}
+    backtrace::init();
+    gdt::init();
+    interrupts::init_idt();
+    time::init();
+    vga_buffer::theme::apply(THEME);
+    for &(sink, level) in SINKS.iter() {
+        console::register(sink, level).expect("the boot sinks have distinct names");
+    }
+    // Only worth it if there is a UART to talk to
+    if serial::init(serial::DEFAULT_BAUD_RATE) {
+        console::register(&serial::SerialSink, LevelFilter::Debug).expect("no other serial sink");
+    }
+    logger::init();
+    log::info!("{} sinks registered, theme {}", SINKS.len(), THEME.name);
+    #[cfg(feature = "screen-tests")]
+    screen_tests::run();
+    #[cfg(feature = "framebuffer")]
+    fb_console::init(vga_graphics::Mode::Graphics640x480x16);
+    status_bar::init();
+    #[cfg(feature = "bench")]
+    vga_buffer::bench::run();
+    interrupts::init();
+    print!("Hello again");
+    print!(", some numbers: {} {}", 42, 1.337);
+    // Everything else happens in the timer interrupt
+    loop {
+        x86_64::instructions::hlt();
+    }
 }
 
 /// This function is called on panic
 #[panic_handler]
 fn panic(info: &PanicInfo) -> ! {
-    println!("{}", info);
-    loop {}
+    let registers = panic_screen::Registers::capture();
+    panic_screen::show(panic_screen::Cause::Panic(info), &registers)
 }
\ No newline at end of file
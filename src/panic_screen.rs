@@ -0,0 +1,271 @@
+//! Report shown when the kernel panics or hits a fatal CPU exception: the whole screen turns blue
+//! with the panic message or exception, its location, the CPU registers, the running task and a
+//! backtrace, all mirrored to the serial port.
+//! The screen gets as many backtrace frames as fit, the serial port all of them, after the kernel
+//! log kept in the record buffer.
+//!
+//! Everything here breaks locks rather than wait for them, the code holding them will never run
+//! again. With the `qemu-exit` feature QEMU is stopped afterwards, otherwise the CPU halts.
+
+use core::arch::asm;
+use core::fmt::{self, Write};
+use core::panic::PanicInfo;
+use core::sync::atomic::{AtomicBool, Ordering};
+use x86_64::instructions::{hlt, interrupts};
+use x86_64::registers::rflags;
+use x86_64::structures::idt::InterruptStackFrame;
+
+use crate::backtrace;
+use crate::console::ring_buffer;
+use crate::serial;
+use crate::status_bar;
+use crate::vga_buffer::emergency;
+use crate::vga_buffer::Color;
+
+/// Register values in the function calling `Registers::capture`
+///
+/// The general purpose registers are only a hint of what the code was working on, the compiler
+/// has already reused most of them by the time the panic handler runs.
+#[derive(Debug, Default, Clone, Copy)]
+#[repr(C)]
+pub struct Registers {
+    pub rax: u64,
+    pub rbx: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    pub rbp: u64,
+    pub rsp: u64,
+    pub r8: u64,
+    pub r9: u64,
+    pub r10: u64,
+    pub r11: u64,
+    pub r12: u64,
+    pub r13: u64,
+    pub r14: u64,
+    pub r15: u64,
+    pub rip: u64,
+    pub rflags: u64,
+    pub cr0: u64,
+    pub cr2: u64,
+    pub cr3: u64,
+    pub cr4: u64,
+}
+
+impl Registers {
+    /// Reads the registers, inlined so `rbp`, `rsp` and `rip` are those of the caller
+    #[inline(always)]
+    pub fn capture() -> Registers {
+        let mut registers = Registers::default();
+        // The offsets follow the field order of the `repr(C)` struct
+        unsafe {
+            asm!(
+                "mov [{0} + 0x00], rax",
+                "mov [{0} + 0x08], rbx",
+                "mov [{0} + 0x10], rcx",
+                "mov [{0} + 0x18], rdx",
+                "mov [{0} + 0x20], rsi",
+                "mov [{0} + 0x28], rdi",
+                "mov [{0} + 0x30], rbp",
+                "mov [{0} + 0x38], rsp",
+                "mov [{0} + 0x40], r8",
+                "mov [{0} + 0x48], r9",
+                "mov [{0} + 0x50], r10",
+                "mov [{0} + 0x58], r11",
+                "mov [{0} + 0x60], r12",
+                "mov [{0} + 0x68], r13",
+                "mov [{0} + 0x70], r14",
+                "mov [{0} + 0x78], r15",
+                "lea {1}, [rip]",
+                "mov [{0} + 0x80], {1}",
+                "mov {1}, cr0",
+                "mov [{0} + 0x90], {1}",
+                "mov {1}, cr2",
+                "mov [{0} + 0x98], {1}",
+                "mov {1}, cr3",
+                "mov [{0} + 0xa0], {1}",
+                "mov {1}, cr4",
+                "mov [{0} + 0xa8], {1}",
+                in(reg) &mut registers as *mut Registers,
+                out(reg) _,
+                options(nostack, preserves_flags),
+            );
+        }
+        registers.rflags = rflags::read_raw();
+        registers
+    }
+
+    /// Registers for an exception: `rip`, `rsp` and `rflags` from its interrupt stack frame and
+    /// the interrupted `rbp`, the others as the handler left them
+    #[inline(always)]
+    pub fn at_exception(frame: &InterruptStackFrame, rbp: u64) -> Registers {
+        let mut registers = Registers::capture();
+        registers.rip = frame.instruction_pointer.as_u64();
+        registers.rsp = frame.stack_pointer.as_u64();
+        registers.rflags = frame.cpu_flags;
+        registers.rbp = rbp;
+        registers
+    }
+
+    fn named(&self) -> [(&'static str, u64); 22] {
+        [
+            ("RAX", self.rax),
+            ("RBX", self.rbx),
+            ("RCX", self.rcx),
+            ("RDX", self.rdx),
+            ("RSI", self.rsi),
+            ("RDI", self.rdi),
+            ("RBP", self.rbp),
+            ("RSP", self.rsp),
+            ("R8", self.r8),
+            ("R9", self.r9),
+            ("R10", self.r10),
+            ("R11", self.r11),
+            ("R12", self.r12),
+            ("R13", self.r13),
+            ("R14", self.r14),
+            ("R15", self.r15),
+            ("RIP", self.rip),
+            ("RFLAGS", self.rflags),
+            ("CR0", self.cr0),
+            ("CR2", self.cr2),
+            ("CR3", self.cr3),
+            ("CR4", self.cr4),
+        ]
+    }
+}
+
+/// Three registers per line fit the narrowest text mode
+impl fmt::Display for Registers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in self.named().chunks(3) {
+            for (name, value) in line {
+                write!(f, "{:>6}={:016x} ", name, value)?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// What stopped the kernel
+pub enum Cause<'a> {
+    Panic(&'a PanicInfo<'a>),
+    /// A CPU exception, with the error code it pushed if any
+    Exception {
+        name: &'static str,
+        error_code: Option<u64>,
+        frame: &'a InterruptStackFrame,
+    },
+}
+
+impl fmt::Display for Cause<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Cause::Panic(info) => write!(f, "{}", info),
+            Cause::Exception { name, frame, .. } => {
+                write!(f, "{} at {:#x}", name, frame.instruction_pointer.as_u64())
+            }
+        }
+    }
+}
+
+/// Set by the first report, a panic or exception while reporting only gets the raw output path
+static REPORTING: AtomicBool = AtomicBool::new(false);
+
+/// Colors of the panic screen
+const FOREGROUND: Color = Color::White;
+const BACKGROUND: Color = Color::Blue;
+
+/// Lines of the report besides the backtrace frames, with a message fitting on one line
+const REPORT_LINES: usize = 18;
+
+/// Shows the report and stops
+pub fn show(cause: Cause, registers: &Registers) -> ! {
+    interrupts::disable();
+    if REPORTING.swap(true, Ordering::Relaxed) {
+        emergency::raw_print(format_args!("\n{} while reporting another one\n", cause));
+        halt();
+    }
+    let mut screen = emergency::take_over_screen(FOREGROUND, BACKGROUND);
+    let (columns, rows) = screen.size();
+    // A line filling the whole width would be followed by an empty one
+    let _ = report(
+        &mut screen,
+        &cause,
+        registers,
+        rows.saturating_sub(REPORT_LINES),
+        columns - 1,
+    );
+    drop(screen);
+    let mut serial = emergency::steal(&serial::SERIAL1);
+    // The serial console may have missed messages, like those from before it was set up
+    let _ = writeln!(serial, "Kernel log:");
+    ring_buffer::dmesg(&mut *serial);
+    let _ = writeln!(serial);
+    let _ = report(
+        &mut *serial,
+        &cause,
+        registers,
+        backtrace::MAX_FRAMES,
+        usize::MAX,
+    );
+    halt()
+}
+
+/// Stops the kernel for good
+pub fn halt() -> ! {
+    #[cfg(feature = "qemu-exit")]
+    crate::qemu::exit(crate::qemu::ExitCode::Failed);
+    loop {
+        hlt();
+    }
+}
+
+/// Writes the report with at most `max_frames` backtrace frames, cut to `width` characters
+fn report(
+    out: &mut dyn fmt::Write,
+    cause: &Cause,
+    registers: &Registers,
+    max_frames: usize,
+    width: usize,
+) -> fmt::Result {
+    writeln!(out, "*** KERNEL PANIC ***")?;
+    writeln!(out)?;
+    match cause {
+        Cause::Panic(info) => {
+            writeln!(out, "{}", info.message())?;
+            match info.location() {
+                Some(location) => writeln!(
+                    out,
+                    "at {}:{}:{}",
+                    location.file(),
+                    location.line(),
+                    location.column()
+                )?,
+                None => writeln!(out, "at an unknown location")?,
+            }
+        }
+        Cause::Exception {
+            name, error_code, ..
+        } => {
+            write!(out, "CPU exception: {}", name)?;
+            match error_code {
+                Some(error_code) => writeln!(out, ", error code {:#x}", error_code)?,
+                None => writeln!(out)?,
+            }
+            write!(out, "at ")?;
+            backtrace::Frame::at(registers.rip).write(out, width.saturating_sub(3))?;
+            writeln!(out)?;
+        }
+    }
+    writeln!(out, "task {}", status_bar::current_task())?;
+    writeln!(out)?;
+    write!(out, "{}", registers)?;
+    writeln!(out)?;
+    writeln!(out, "Backtrace:")?;
+    backtrace::print_from(out, registers.rbp, max_frames, width)?;
+    writeln!(out)?;
+    writeln!(out, "System halted.")
+}
@@ -0,0 +1,73 @@
+//! PC speaker driven by channel 2 of the programmable interval timer (PIT).
+
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+
+use crate::interrupts::TIMER_FREQUENCY;
+use crate::pit;
+
+/// Tone used for the terminal bell (`\x07`)
+const BELL_FREQUENCY: u32 = 880;
+const BELL_DURATION_MS: u32 = 100;
+const BELL_TICKS: u32 = BELL_DURATION_MS * TIMER_FREQUENCY / 1000;
+
+/// Port reads spent waiting for one edge of the channel 2 output, at about a microsecond per read
+/// a bit more than the half period of the lowest tone (19 Hz)
+const POLLS_PER_EDGE: usize = 30_000;
+
+/// Set by `bell`, cleared by the timer tick starting the tone
+static BELL_PENDING: AtomicBool = AtomicBool::new(false);
+/// Timer ticks until the bell tone stops, 0 while it isn't playing
+static BELL_TICKS_LEFT: AtomicU32 = AtomicU32::new(0);
+
+/// Starts a continuous tone at `frequency` Hz, until `stop` is called
+pub fn play(frequency: u32) {
+    pit::start(pit::CHANNEL_2, pit::CHANNEL_2_SQUARE_WAVE, pit::divisor(frequency));
+    pit::gate_channel_2(pit::GATE_2 | pit::SPEAKER);
+}
+
+/// Silences the speaker
+pub fn stop() {
+    pit::gate_channel_2(0);
+}
+
+/// Plays a tone for `milliseconds` and blocks until it is over
+///
+/// The duration is measured by counting the periods of the square wave itself on the channel 2
+/// output bit. Each edge is waited for at most `POLLS_PER_EDGE` reads, so a PIT that doesn't
+/// toggle it cuts the tone short instead of hanging.
+#[allow(dead_code)]
+pub fn beep(frequency: u32, milliseconds: u32) {
+    play(frequency);
+    let periods = u64::from(frequency) * u64::from(milliseconds) / 1000;
+    let wait_for = |level: bool| (0..POLLS_PER_EDGE).any(|_| pit::channel_2_output() == level);
+    for _ in 0..periods {
+        if !wait_for(false) || !wait_for(true) {
+            break;
+        }
+    }
+    stop();
+}
+
+/// The terminal bell
+///
+/// Only noted here: the next timer tick starts the tone and a later one stops it, so printing
+/// `\x07` neither waits for the tone nor keeps the console locked meanwhile.
+pub fn bell() {
+    BELL_PENDING.store(true, Ordering::Relaxed);
+}
+
+/// Called from the timer interrupt handler, plays the bell when asked to
+pub fn tick() {
+    if BELL_PENDING.swap(false, Ordering::Relaxed) {
+        play(BELL_FREQUENCY);
+        BELL_TICKS_LEFT.store(BELL_TICKS, Ordering::Relaxed);
+        return;
+    }
+    let ticks_left = BELL_TICKS_LEFT.load(Ordering::Relaxed);
+    if ticks_left > 0 {
+        BELL_TICKS_LEFT.store(ticks_left - 1, Ordering::Relaxed);
+        if ticks_left == 1 {
+            stop();
+        }
+    }
+}
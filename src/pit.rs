@@ -0,0 +1,67 @@
+//! Port I/O helpers for the programmable interval timer (PIT), shared by the timer interrupt, the
+//! PC speaker and the TSC calibration.
+//!
+//! Channel 0 fires IRQ 0. Channel 2 is gated by keyboard controller port B, which also connects
+//! its output to the speaker and lets it be read back.
+
+use x86_64::instructions::port::Port;
+
+/// Input clock of the PIT in Hz
+pub const FREQUENCY: u32 = 1_193_182;
+
+pub const CHANNEL_0: u16 = 0x40;
+pub const CHANNEL_2: u16 = 0x42;
+const COMMAND: u16 = 0x43;
+/// Keyboard controller port B
+const PORT_B: u16 = 0x61;
+
+/// Channel 0, lobyte/hibyte access, mode 2 (rate generator), binary
+pub const CHANNEL_0_RATE_GENERATOR: u8 = 0b0011_0100;
+/// Channel 2, lobyte/hibyte access, mode 0 (output set at the end of the count), binary
+pub const CHANNEL_2_ONE_SHOT: u8 = 0b1011_0000;
+/// Channel 2, lobyte/hibyte access, mode 3 (square wave), binary
+pub const CHANNEL_2_SQUARE_WAVE: u8 = 0b1011_0110;
+
+/// Bit 0 of port B gates channel 2
+pub const GATE_2: u8 = 0b01;
+/// Bit 1 of port B connects the output of channel 2 to the speaker
+pub const SPEAKER: u8 = 0b10;
+/// Bit 5 of port B reflects the output of channel 2
+const CHANNEL_2_OUTPUT: u8 = 1 << 5;
+
+/// Count giving `frequency` Hz, the 16 bit counter doesn't go below 19 Hz
+pub fn divisor(frequency: u32) -> u16 {
+    (FREQUENCY / frequency.max(19)).min(0xffff) as u16
+}
+
+/// Sets the mode of a channel with one of the commands above and starts it counting `count`
+pub fn start(channel: u16, command: u8, count: u16) {
+    let mut command_port = Port::new(COMMAND);
+    let mut channel = Port::new(channel);
+    unsafe {
+        command_port.write(command);
+        channel.write((count & 0xff) as u8);
+        channel.write((count >> 8) as u8);
+    }
+}
+
+/// Sets the `GATE_2` and `SPEAKER` bits of port B to `bits` and returns the previous value of the
+/// port, for `restore_port_b`
+pub fn gate_channel_2(bits: u8) -> u8 {
+    let mut port: Port<u8> = Port::new(PORT_B);
+    unsafe {
+        let saved = port.read();
+        port.write((saved & !(GATE_2 | SPEAKER)) | bits);
+        saved
+    }
+}
+
+pub fn restore_port_b(value: u8) {
+    unsafe { Port::new(PORT_B).write(value) }
+}
+
+/// Level of the channel 2 output
+pub fn channel_2_output() -> bool {
+    let mut port: Port<u8> = Port::new(PORT_B);
+    unsafe { port.read() & CHANNEL_2_OUTPUT != 0 }
+}
@@ -0,0 +1,176 @@
+//! Parser for PC Screen Font files (PSF1 and PSF2), the bitmap fonts of the Linux console.
+//!
+//! A font is a header followed by the glyph bitmaps, one bit per pixel with each row padded to a
+//! whole byte, and optionally a table mapping every glyph to the characters it represents.
+
+use core::convert::{TryFrom, TryInto};
+use core::str;
+
+const PSF1_MAGIC: [u8; 2] = [0x36, 0x04];
+const PSF1_MODE_512: u8 = 0x01;
+const PSF1_MODE_HAS_TABLE: u8 = 0x02;
+const PSF1_MODE_HAS_SEQUENCES: u8 = 0x04;
+const PSF1_HEADER_SIZE: usize = 4;
+const PSF1_SEPARATOR: u16 = 0xffff;
+const PSF1_SEQUENCE_START: u16 = 0xfffe;
+
+const PSF2_MAGIC: [u8; 4] = [0x72, 0xb5, 0x4a, 0x86];
+const PSF2_HAS_UNICODE_TABLE: u32 = 0x01;
+const PSF2_SEPARATOR: u8 = 0xff;
+const PSF2_SEQUENCE_START: u8 = 0xfe;
+
+/// The font built into the kernel: 8x16 glyphs in code page 437 order, from the public domain X11
+/// "fixed" font (see `tools/bdf2psf.py`)
+pub static DEFAULT_FONT: &[u8] = include_bytes!("../fonts/cp437-8x16.psf");
+
+/// The same in 8x8, for the text modes with 50 and 60 rows
+pub static FONT_8X8: &[u8] = include_bytes!("../fonts/cp437-8x8.psf");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum UnicodeTable {
+    None,
+    /// UCS-2 little endian entries
+    Psf1,
+    /// UTF-8 entries
+    Psf2,
+}
+
+/// A parsed font, borrowing the bytes of the file
+#[derive(Debug, Clone, Copy)]
+pub struct Font<'a> {
+    glyphs: &'a [u8],
+    table: &'a [u8],
+    table_kind: UnicodeTable,
+    glyph_count: usize,
+    bytes_per_glyph: usize,
+    width: usize,
+    height: usize,
+}
+
+#[allow(dead_code)]
+impl<'a> Font<'a> {
+    /// Parses a PSF1 or PSF2 file, returns `None` if it is not one or is truncated
+    pub fn parse(data: &'a [u8]) -> Option<Font<'a>> {
+        if data.starts_with(&PSF1_MAGIC) {
+            Font::parse_psf1(data)
+        } else if data.starts_with(&PSF2_MAGIC) {
+            Font::parse_psf2(data)
+        } else {
+            None
+        }
+    }
+
+    fn parse_psf1(data: &'a [u8]) -> Option<Font<'a>> {
+        let mode = *data.get(2)?;
+        let height = *data.get(3)? as usize;
+        let glyph_count = if mode & PSF1_MODE_512 != 0 { 512 } else { 256 };
+        let end = PSF1_HEADER_SIZE + glyph_count * height;
+        let has_table = mode & (PSF1_MODE_HAS_TABLE | PSF1_MODE_HAS_SEQUENCES) != 0;
+        Some(Font {
+            glyphs: data.get(PSF1_HEADER_SIZE..end)?,
+            table: &data[end..],
+            table_kind: if has_table { UnicodeTable::Psf1 } else { UnicodeTable::None },
+            glyph_count,
+            bytes_per_glyph: height,
+            width: 8,
+            height,
+        })
+    }
+
+    fn parse_psf2(data: &'a [u8]) -> Option<Font<'a>> {
+        let field = |index: usize| -> Option<usize> {
+            let bytes = data.get(index * 4..index * 4 + 4)?;
+            Some(u32::from_le_bytes(bytes.try_into().ok()?) as usize)
+        };
+        let header_size = field(2)?;
+        let flags = field(3)? as u32;
+        let glyph_count = field(4)?;
+        let bytes_per_glyph = field(5)?;
+        let height = field(6)?;
+        let width = field(7)?;
+        if width == 0 || bytes_per_glyph < height * width.div_ceil(8) {
+            return None;
+        }
+        let end = header_size.checked_add(glyph_count.checked_mul(bytes_per_glyph)?)?;
+        let has_table = flags & PSF2_HAS_UNICODE_TABLE != 0;
+        Some(Font {
+            glyphs: data.get(header_size..end)?,
+            table: &data[end..],
+            table_kind: if has_table { UnicodeTable::Psf2 } else { UnicodeTable::None },
+            glyph_count,
+            bytes_per_glyph,
+            width,
+            height,
+        })
+    }
+
+    /// Width of a glyph in pixels
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of a glyph in pixels
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn glyph_count(&self) -> usize {
+        self.glyph_count
+    }
+
+    /// Bytes per glyph row, the leftmost pixel is the most significant bit of the first byte
+    pub fn bytes_per_row(&self) -> usize {
+        self.width.div_ceil(8)
+    }
+
+    /// The bitmap of glyph `index`, `height` rows of `bytes_per_row` bytes
+    pub fn glyph(&self, index: usize) -> Option<&'a [u8]> {
+        if index >= self.glyph_count {
+            return None;
+        }
+        let start = index * self.bytes_per_glyph;
+        Some(&self.glyphs[start..start + self.height * self.bytes_per_row()])
+    }
+
+    /// Finds the glyph showing `c`
+    ///
+    /// Without a unicode table the glyph index is taken to be the character code.
+    pub fn glyph_index(&self, c: char) -> Option<usize> {
+        match self.table_kind {
+            UnicodeTable::None => Some(c as usize).filter(|&index| index < self.glyph_count),
+            UnicodeTable::Psf1 => self.psf1_glyph_index(c),
+            UnicodeTable::Psf2 => self.psf2_glyph_index(c),
+        }
+    }
+
+    fn psf1_glyph_index(&self, c: char) -> Option<usize> {
+        // UCS-2 can't hold anything past the basic multilingual plane
+        let code = u16::try_from(c as u32).ok()?;
+        let mut glyph = 0;
+        let mut in_sequence = false;
+        for entry in self.table.chunks_exact(2) {
+            match u16::from_le_bytes([entry[0], entry[1]]) {
+                PSF1_SEPARATOR => {
+                    glyph += 1;
+                    in_sequence = false;
+                }
+                PSF1_SEQUENCE_START => in_sequence = true,
+                value if value == code && !in_sequence => return Some(glyph),
+                _ => {}
+            }
+        }
+        None
+    }
+
+    fn psf2_glyph_index(&self, c: char) -> Option<usize> {
+        // 0xfe and 0xff never occur in UTF-8, so the table splits cleanly on them
+        self.table
+            .split(|&byte| byte == PSF2_SEPARATOR)
+            .take(self.glyph_count)
+            .position(|entry| {
+                // Combining sequences come after the single characters and are not supported
+                let singles = entry.split(|&byte| byte == PSF2_SEQUENCE_START).next().unwrap_or(&[]);
+                str::from_utf8(singles).is_ok_and(|s| s.chars().any(|entry| entry == c))
+            })
+    }
+}
@@ -0,0 +1,22 @@
+//! Leaving QEMU from inside the kernel, through its `isa-debug-exit` device.
+//!
+//! QEMU has to be started with `-device isa-debug-exit,iobase=0xf4,iosize=0x04`. Its exit status
+//! is then `(code << 1) | 1`, so 33 for `ExitCode::Success` and 35 for `ExitCode::Failed`.
+
+use x86_64::instructions::port::Port;
+
+/// I/O port the `isa-debug-exit` device listens on
+const ISA_DEBUG_EXIT_PORT: u16 = 0xf4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+#[allow(dead_code)]
+pub enum ExitCode {
+    Success = 0x10,
+    Failed = 0x11,
+}
+
+/// Stops QEMU with `code`, returns only if there is no exit device
+pub fn exit(code: ExitCode) {
+    unsafe { Port::new(ISA_DEBUG_EXIT_PORT).write(code as u32) };
+}
@@ -0,0 +1,188 @@
+//! Scenarios for the golden-file screen tests, run at boot instead of the kernel with the
+//! `screen-tests` feature.
+//!
+//! Each scenario draws through `WRITER` from a reset console, then the screen is captured to the
+//! serial port under the scenario name, and `tools/screen_test.py` compares the captures with the
+//! golden files. The checks that follow assert what a capture can't show, each reports a
+//! `CHECK <name> ok` or `CHECK <name> FAILED: <reason>` line. QEMU is stopped once all of them ran,
+//! with `ExitCode::Failed` if a check failed.
+
+use core::fmt::Write;
+
+use crate::qemu::{self, ExitCode};
+use crate::serial_println;
+use crate::status_bar;
+use crate::vga_buffer::capture::Snapshot;
+use crate::vga_buffer::{self, capture, Color, ColorCode, Writer, CONSOLES, WRITER};
+
+type Scenario = fn(&mut Writer);
+type Check = fn() -> Result<(), &'static str>;
+
+/// Each scenario with the name its capture is compared under
+const SCENARIOS: [(&str, Scenario); 7] = [
+    ("wrap", wrap),
+    ("scroll", scroll),
+    ("colors", colors),
+    ("control_characters", control_characters),
+    ("cp437", cp437),
+    ("scroll_region", scroll_region),
+    ("escape_sequences", escape_sequences),
+];
+
+/// Each check with the name it is reported under
+const CHECKS: [(&str, Check); 2] = [
+    ("status_bar_keeps_scrollback", status_bar_keeps_scrollback),
+    ("fresh_console_cursor", fresh_console_cursor),
+];
+
+/// Runs and captures every scenario, runs the checks, then exits QEMU
+///
+/// Without the exit device the kernel boots on after the checks.
+pub fn run() {
+    for &(name, scenario) in SCENARIOS.iter() {
+        {
+            let mut writer = WRITER.lock();
+            // `ESC c` clears the screen and restores the default colors and scroll region
+            writer.write_string("\x1bc");
+            scenario(&mut writer);
+        }
+        capture::to_serial(name);
+    }
+    let mut exit_code = ExitCode::Success;
+    for &(name, check) in CHECKS.iter() {
+        match check() {
+            Ok(()) => serial_println!("CHECK {} ok", name),
+            Err(reason) => {
+                serial_println!("CHECK {} FAILED: {}", name, reason);
+                exit_code = ExitCode::Failed;
+            }
+        }
+    }
+    qemu::exit(exit_code);
+}
+
+/// Long lines continue on the next row, a full row followed by a newline leaves no empty one
+fn wrap(writer: &mut Writer) {
+    writer.write_string(
+        "This line is longer than the screen is wide, so the writer has to continue it on the \
+         next row instead of cutting it off.\n",
+    );
+    let (columns, _) = writer.size();
+    for _ in 0..columns {
+        writer.write_byte(b'=');
+    }
+    writer.write_string("\nafter a full row\n");
+}
+
+/// Once the screen is full, the oldest rows scroll off the top
+fn scroll(writer: &mut Writer) {
+    let (_, rows) = writer.size();
+    for line in 0..rows + 5 {
+        let _ = writeln!(writer, "line {}", line);
+    }
+}
+
+/// SGR colors, bold, reverse video and text drawn in a given color code
+fn colors(writer: &mut Writer) {
+    writer.write_string(
+        "\x1b[31mred\x1b[0m \x1b[1;32mbold green\x1b[0m \x1b[44;93myellow on blue\x1b[0m\n",
+    );
+    writer.write_string("\x1b[7mreverse\x1b[27m normal \x1b[1mbold \x1b[22mnot bold\n");
+    let color_code = ColorCode::new(Color::White, Color::Magenta);
+    writer.write_at(3, 10, "write_at", color_code);
+}
+
+/// Tabs, carriage returns and backspaces
+fn control_characters(writer: &mut Writer) {
+    writer.write_string("a\tb\tc\n");
+    writer.write_string("\tindented\n");
+    writer.write_string("12345678\tnext stop\n");
+    writer.write_string("overwritten\rOVER\n");
+    writer.write_string("backspace\x08\x08\x08\x08XYZ\n");
+}
+
+/// Unicode characters with a CP437 glyph, and one without
+fn cp437(writer: &mut Writer) {
+    writer.write_string("┌──┐ ╔══╗ ░▒▓█\n");
+    writer.write_string("│ok│ ║ok║ é ü ñ ß π ½ °\n");
+    writer.write_string("└──┘ ╚══╝ smiley: \u{263a} no glyph: \u{1f600}\n");
+}
+
+/// Scrolling confined to a band of rows leaves the others alone
+fn scroll_region(writer: &mut Writer) {
+    let (_, rows) = writer.size();
+    let fixed = ColorCode::new(Color::Black, Color::LightGray);
+    writer.write_at(0, 0, "fixed top row", fixed);
+    writer.write_at(rows - 1, 0, "fixed bottom row", fixed);
+    writer.set_scroll_region(1, rows - 2);
+    for line in 0..rows + 5 {
+        let _ = writeln!(writer, "scrolled {}", line);
+    }
+}
+
+/// Cursor movement, erasing, saving and restoring the cursor, and turning bold off again
+///
+/// Every part is placed with CUP, from the top of the screen down, since erasing the display
+/// reaches the parts above (ED 1) or below (ED 0) the cursor.
+fn escape_sequences(writer: &mut Writer) {
+    // ED 1: the first row and the start of the second one go, the third one stays
+    writer.write_string(
+        "\x1b[1;1Herased row\x1b[2;1Herased, kept\x1b[3;1Hkept row\x1b[2;7H\x1b[1J",
+    );
+    // CUP, then CUU / CUD / CUF / CUB from each character to the next, CUB stopping at column 1
+    writer.write_string("\x1b[6;5Hx\x1b[1Ay\x1b[3Bz\x1b[10Cw\x1b[20Dv");
+    // EL 0, 1 and 2, the last one in the blue background it was given
+    writer.write_string("\x1b[10;1Hkept, erased\x1b[10;5H\x1b[K");
+    writer.write_string("\x1b[11;1Herased, kept\x1b[11;7H\x1b[1K");
+    writer.write_string("\x1b[12;1Herased line\x1b[44m\x1b[2K\x1b[0m");
+    // ESC 7 / ESC 8 and CSI s / CSI u bring back the position and the colors
+    writer.write_string(
+        "\x1b[14;1Hsaved with ESC 7:\x1b7\x1b[31m\x1b[16;1Hred elsewhere\x1b8 restored",
+    );
+    writer.write_string(
+        "\x1b[15;1Hsaved with CSI s:\x1b[s\x1b[32m\x1b[17;1Hgreen elsewhere\x1b[u restored",
+    );
+    // Bold off keeps a color that was bright before bold, and darkens one bold brightened
+    writer.write_string("\x1b[19;1H\x1b[93mbright \x1b[1mbold \x1b[22mnot bold\x1b[0m ");
+    writer.write_string("\x1b[33mdark \x1b[1mbold \x1b[22mnot bold\x1b[0m");
+    // ED 0: the end of the second row and the rows below go, the first row stays
+    writer.write_string(
+        "\x1b[21;1Hkept row\x1b[22;1Hkept, erased\x1b[23;1Herased row\x1b[22;5H\x1b[J",
+    );
+}
+
+/// The status bar redrawing itself doesn't bring a scrolled back view to the live screen
+fn status_bar_keeps_scrollback() -> Result<(), &'static str> {
+    let offset = {
+        let mut writer = WRITER.lock();
+        writer.write_string("\x1bc");
+        let (_, rows) = writer.size();
+        writer.set_scroll_region(1, rows - 1);
+        for line in 0..2 * rows {
+            let _ = writeln!(writer, "line {}", line);
+        }
+        writer.page_up();
+        writer.view_offset()
+    };
+    if offset == 0 {
+        return Err("page_up didn't scroll back");
+    }
+    status_bar::refresh();
+    if WRITER.lock().view_offset() != offset {
+        return Err("the refresh moved the view");
+    }
+    Ok(())
+}
+
+/// The cursor of a console nothing was written to yet sits on a cell it is visible on
+fn fresh_console_cursor() -> Result<(), &'static str> {
+    vga_buffer::switch_console(1);
+    let (row, col) = CONSOLES[1].lock().position();
+    let cell = Snapshot::take().cell(row, col);
+    vga_buffer::switch_console(0);
+    // The cursor is drawn in the foreground color of the cell
+    if cell.color_code().foreground() == cell.color_code().background() {
+        return Err("the cell under the cursor has the same foreground and background");
+    }
+    Ok(())
+}
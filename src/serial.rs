@@ -0,0 +1,161 @@
+//! Serial console on a 16550 UART, for headless runs: QEMU's `-serial stdio` shows what the
+//! kernel sends to COM1.
+//!
+//! `init` sets the port up, `serial_print!`/`serial_println!` write to it, and registering
+//! `SerialSink` as a console sink sends everything printed with `print!` there as well.
+
+use core::fmt;
+use spin::Mutex;
+use x86_64::instructions::interrupts;
+use x86_64::instructions::port::Port;
+
+use crate::console::Console;
+
+/// I/O base of the first serial port
+pub const COM1: u16 = 0x3f8;
+
+/// 115200 baud is the UART clock divided by 1, the fastest it goes
+pub const DEFAULT_BAUD_RATE: u32 = 115_200;
+
+// Registers, as offsets from the base port
+const DATA: u16 = 0;
+const INTERRUPT_ENABLE: u16 = 1;
+/// Divisor latch, in place of the data and interrupt enable registers while DLAB is set
+const DIVISOR_LOW: u16 = 0;
+const DIVISOR_HIGH: u16 = 1;
+const FIFO_CONTROL: u16 = 2;
+const LINE_CONTROL: u16 = 3;
+const MODEM_CONTROL: u16 = 4;
+const LINE_STATUS: u16 = 5;
+
+/// Divisor latch access bit of the line control register
+const LINE_CONTROL_DLAB: u8 = 0x80;
+/// 8 data bits, no parity, one stop bit
+const LINE_CONTROL_8N1: u8 = 0x03;
+/// Enable and clear both FIFOs, interrupt threshold of 14 bytes
+const FIFO_ENABLE_CLEAR_14: u8 = 0xc7;
+/// DTR, RTS and OUT2 set
+const MODEM_CONTROL_READY: u8 = 0x0b;
+/// Same as ready but looped back onto itself, for the self test
+const MODEM_CONTROL_LOOPBACK: u8 = 0x1e;
+/// Line status bit telling the transmit holding register is empty
+const LINE_STATUS_TRANSMIT_EMPTY: u8 = 0x20;
+
+/// The UART input clock divided by 16, the divisor latch divides it further down to the baud rate
+const UART_BASE_RATE: u32 = 115_200;
+
+/// Gives up sending a byte after this many polls, rather than hang on a stuck port
+const TRANSMIT_TIMEOUT: usize = 100_000;
+
+pub static SERIAL1: Mutex<SerialPort> = Mutex::new(SerialPort::new(COM1));
+
+/// Sets COM1 up at `baud_rate`, returns `false` if there is no working UART
+///
+/// The rate is rounded to one the UART can do, that is 115200 divided by a whole number.
+pub fn init(baud_rate: u32) -> bool {
+    interrupts::without_interrupts(|| SERIAL1.lock().init(baud_rate))
+}
+
+pub struct SerialPort {
+    base: u16,
+    /// Set once `init` found the UART working, bytes sent before that are dropped
+    ready: bool,
+}
+
+impl SerialPort {
+    /// A port at I/O base `base`, which needs `init` before it sends anything
+    pub const fn new(base: u16) -> SerialPort {
+        SerialPort { base, ready: false }
+    }
+
+    /// Programs the line settings (8N1 at `baud_rate`) and checks the UART in loopback mode
+    pub fn init(&mut self, baud_rate: u32) -> bool {
+        if baud_rate == 0 {
+            return false;
+        }
+        let divisor = (UART_BASE_RATE / baud_rate).clamp(1, u16::MAX as u32) as u16;
+        self.write(INTERRUPT_ENABLE, 0x00);
+        self.write(LINE_CONTROL, LINE_CONTROL_DLAB);
+        self.write(DIVISOR_LOW, divisor as u8);
+        self.write(DIVISOR_HIGH, (divisor >> 8) as u8);
+        self.write(LINE_CONTROL, LINE_CONTROL_8N1);
+        self.write(FIFO_CONTROL, FIFO_ENABLE_CLEAR_14);
+
+        // A byte sent in loopback mode must come back unchanged
+        self.write(MODEM_CONTROL, MODEM_CONTROL_LOOPBACK);
+        self.write(DATA, 0xae);
+        self.ready = self.read(DATA) == 0xae;
+        self.write(MODEM_CONTROL, MODEM_CONTROL_READY);
+        self.ready
+    }
+
+    /// Sends one byte, waiting for the transmitter to be free
+    pub fn send(&mut self, byte: u8) {
+        if !self.ready {
+            return;
+        }
+        for _ in 0..TRANSMIT_TIMEOUT {
+            if self.read(LINE_STATUS) & LINE_STATUS_TRANSMIT_EMPTY != 0 {
+                self.write(DATA, byte);
+                return;
+            }
+        }
+    }
+
+    fn read(&mut self, register: u16) -> u8 {
+        unsafe { Port::new(self.base + register).read() }
+    }
+
+    fn write(&mut self, register: u16, value: u8) {
+        unsafe { Port::new(self.base + register).write(value) }
+    }
+}
+
+/// Terminals expect `\r\n`, so a carriage return is sent before every newline
+impl fmt::Write for SerialPort {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        for byte in s.bytes() {
+            if byte == b'\n' {
+                self.send(b'\r');
+            }
+            self.send(byte);
+        }
+        Ok(())
+    }
+}
+
+/// Console sink writing to `SERIAL1`
+pub struct SerialSink;
+
+impl Console for SerialSink {
+    fn name(&self) -> &'static str {
+        "serial"
+    }
+
+    fn write_str(&self, s: &str) {
+        use core::fmt::Write;
+        let _ = SERIAL1.lock().write_str(s);
+    }
+}
+
+/// Like `print!`, but prints to the first serial port.
+#[macro_export]
+macro_rules! serial_print {
+    ($($arg:tt)*) => ($crate::serial::_print(format_args!($($arg)*)));
+}
+
+/// Like `println!`, but prints to the first serial port.
+#[macro_export]
+macro_rules! serial_println {
+    () => ($crate::serial_print!("\n"));
+    ($($arg:tt)*) => ($crate::serial_print!("{}\n", format_args!($($arg)*)));
+}
+
+/// Prints the given formatted string to `SERIAL1`, with interrupts disabled like `console::_print`.
+#[doc(hidden)]
+pub fn _print(args: fmt::Arguments) {
+    use core::fmt::Write;
+    interrupts::without_interrupts(|| {
+        SERIAL1.lock().write_fmt(args).unwrap();
+    });
+}
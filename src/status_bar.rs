@@ -0,0 +1,132 @@
+//! Kernel status line, kept on the top row of the kernel console.
+//!
+//! The counters are updated by the rest of the kernel through the functions of this module and the
+//! line is redrawn every `REFRESH_TICKS` timer ticks (see `interrupts::TIMER_FREQUENCY`), so a
+//! frozen status bar means a stuck kernel.
+
+use core::fmt;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use spin::Mutex;
+
+use crate::vga_buffer::{Color, ColorCode, Region, Writer, WRITER};
+
+/// Number of timer ticks between two redraws
+const REFRESH_TICKS: u64 = 10;
+
+/// Marks a statistic that no subsystem has reported yet
+const UNKNOWN: usize = usize::MAX;
+
+static TICKS: AtomicU64 = AtomicU64::new(0);
+static INTERRUPTS: AtomicU64 = AtomicU64::new(0);
+static FREE_FRAMES: AtomicUsize = AtomicUsize::new(UNKNOWN);
+static HEAP_USED: AtomicUsize = AtomicUsize::new(UNKNOWN);
+static HEAP_SIZE: AtomicUsize = AtomicUsize::new(UNKNOWN);
+static CURRENT_TASK: Mutex<&str> = Mutex::new("kernel");
+
+/// Reserves the top row of the kernel console for the status bar and draws it
+pub fn init() {
+    let mut writer = WRITER.lock();
+    let (_, rows) = writer.size();
+    writer.set_scroll_region(1, rows - 1);
+    draw(&mut writer);
+}
+
+/// Timer ticks counted since boot
+pub fn ticks() -> u64 {
+    TICKS.load(Ordering::Relaxed)
+}
+
+/// Called from the timer interrupt handler, counts as one interrupt as well
+pub fn tick() {
+    let ticks = TICKS.fetch_add(1, Ordering::Relaxed) + 1;
+    count_interrupt();
+    if ticks.is_multiple_of(REFRESH_TICKS) {
+        refresh();
+    }
+}
+
+/// To be called from every other interrupt handler
+pub fn count_interrupt() {
+    INTERRUPTS.fetch_add(1, Ordering::Relaxed);
+}
+
+#[allow(dead_code)]
+pub fn set_free_frames(frames: usize) {
+    FREE_FRAMES.store(frames, Ordering::Relaxed);
+}
+
+#[allow(dead_code)]
+pub fn set_heap_usage(used: usize, size: usize) {
+    HEAP_USED.store(used, Ordering::Relaxed);
+    HEAP_SIZE.store(size, Ordering::Relaxed);
+}
+
+#[allow(dead_code)]
+pub fn set_current_task(name: &'static str) {
+    *CURRENT_TASK.lock() = name;
+}
+
+/// Name of the running task, `"?"` if it is being changed
+pub fn current_task() -> &'static str {
+    CURRENT_TASK.try_lock().map(|task| *task).unwrap_or("?")
+}
+
+/// Redraws the status bar, unless the console is busy in which case the next refresh will do it
+///
+/// Waiting for the lock here could deadlock when called from an interrupt handler.
+pub fn refresh() {
+    if let Some(mut writer) = WRITER.try_lock() {
+        draw(&mut writer);
+    }
+}
+
+fn draw(writer: &mut Writer) {
+    let (columns, _) = writer.size();
+    let mut region = Region::new(0, 0, 1, columns, ColorCode::new(Color::Black, Color::LightGray));
+    region.set_wrap(false);
+    writer.clear_region(&mut region);
+
+    let task = current_task();
+    let mut line = RegionWriter {
+        writer,
+        region: &mut region,
+    };
+    // Writing to the screen can't fail
+    let _ = fmt::write(
+        &mut line,
+        format_args!(
+            " up {} | frames {} | heap {}/{} | task {} | irq {}",
+            ticks(),
+            Stat(FREE_FRAMES.load(Ordering::Relaxed)),
+            Stat(HEAP_USED.load(Ordering::Relaxed)),
+            Stat(HEAP_SIZE.load(Ordering::Relaxed)),
+            task,
+            INTERRUPTS.load(Ordering::Relaxed),
+        ),
+    );
+}
+
+/// A statistic that prints as `-` while unknown
+struct Stat(usize);
+
+impl fmt::Display for Stat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            UNKNOWN => f.write_str("-"),
+            value => write!(f, "{}", value),
+        }
+    }
+}
+
+/// Formats straight into a region of the console
+struct RegionWriter<'a> {
+    writer: &'a mut Writer,
+    region: &'a mut Region,
+}
+
+impl fmt::Write for RegionWriter<'_> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.writer.write_region(self.region, s);
+        Ok(())
+    }
+}
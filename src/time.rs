@@ -0,0 +1,80 @@
+//! Time since boot, read from the CPU time stamp counter (TSC).
+//!
+//! `init` measures how fast the TSC runs against a one-shot count of PIT channel 2, whose input
+//! clock is fixed, so timestamps are in real time from the first message on without waiting for
+//! the timer interrupt.
+
+use core::arch::x86_64::_rdtsc;
+use core::fmt;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::pit;
+
+/// Length of the calibration count
+const CALIBRATION_MS: u32 = 10;
+
+/// Status reads of the calibration count before giving up on the PIT, a read takes about a
+/// microsecond so this is way past `CALIBRATION_MS`
+const CALIBRATION_POLLS: usize = 1_000_000;
+
+/// Assumed if the calibration fails, timestamps are then only roughly right
+const FALLBACK_TSC_PER_US: u64 = 1000;
+
+/// TSC value at `init`
+static BOOT_TSC: AtomicU64 = AtomicU64::new(0);
+/// TSC increments per microsecond, 0 until `init`
+static TSC_PER_US: AtomicU64 = AtomicU64::new(0);
+
+/// Calibrates the TSC, timestamps are 0 until then
+pub fn init() {
+    let per_us = calibrate().unwrap_or(FALLBACK_TSC_PER_US).max(1);
+    BOOT_TSC.store(unsafe { _rdtsc() }, Ordering::Relaxed);
+    TSC_PER_US.store(per_us, Ordering::Relaxed);
+}
+
+/// Time elapsed since `init`
+pub fn now() -> Timestamp {
+    let per_us = TSC_PER_US.load(Ordering::Relaxed);
+    if per_us == 0 {
+        return Timestamp::from_micros(0);
+    }
+    let elapsed = unsafe { _rdtsc() }.saturating_sub(BOOT_TSC.load(Ordering::Relaxed));
+    Timestamp::from_micros(elapsed / per_us)
+}
+
+/// TSC increments per microsecond, `None` if channel 2 never reached the end of its count
+fn calibrate() -> Option<u64> {
+    let count = (pit::FREQUENCY * CALIBRATION_MS / 1000) as u16;
+    let saved = pit::gate_channel_2(pit::GATE_2);
+    pit::start(pit::CHANNEL_2, pit::CHANNEL_2_ONE_SHOT, count);
+    let start = unsafe { _rdtsc() };
+    let done = (0..CALIBRATION_POLLS).any(|_| pit::channel_2_output());
+    let cycles = unsafe { _rdtsc() }.saturating_sub(start);
+    pit::restore_port_b(saved);
+    if !done {
+        return None;
+    }
+    Some(cycles / (u64::from(CALIBRATION_MS) * 1000))
+}
+
+/// Microseconds since boot, shown as seconds like `    1.204518`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Timestamp {
+    micros: u64,
+}
+
+impl Timestamp {
+    pub const fn from_micros(micros: u64) -> Timestamp {
+        Timestamp { micros }
+    }
+
+    pub const fn as_micros(self) -> u64 {
+        self.micros
+    }
+}
+
+impl fmt::Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{:>5}.{:06}", self.micros / 1_000_000, self.micros % 1_000_000)
+    }
+}
@@ -0,0 +1,188 @@
+//! Text mode widgets for the kernel diagnostics and setup tools: framed windows, labels, progress
+//! bars, scrollable lists, menus and dialog boxes.
+//!
+//! Widgets draw into a `Surface`, an off-screen grid of `ScreenChar`s, which `Surface::present`
+//! then copies to a console in one go. Nothing is allocated: widgets borrow their texts and items,
+//! and a `Form` borrows the widgets it moves the keyboard focus between.
+//!
+//! There is no keyboard driver here, whatever reads the keyboard translates its keys to `Key` and
+//! hands them to the focused widget through `Form::handle_key` (or to a lone widget directly).
+
+use crate::vga_buffer::{Color, ColorCode};
+
+// Not all of the widgets have users in the kernel yet
+#[allow(unused_imports)]
+pub use self::{
+    dialog::Dialog,
+    label::{Align, Label},
+    list::List,
+    menu::{Menu, MenuItem},
+    progress::ProgressBar,
+    surface::{Border, Rect, Surface},
+    window::Window,
+};
+
+mod dialog;
+mod label;
+mod list;
+mod menu;
+mod progress;
+mod surface;
+mod window;
+
+/// Keys the widgets react to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    PageUp,
+    PageDown,
+    Home,
+    End,
+    Tab,
+    /// Shift+Tab
+    BackTab,
+    Enter,
+    Escape,
+    Char(char),
+}
+
+/// What a widget did with a key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum Event {
+    /// The key means nothing to the widget
+    Ignored,
+    /// The widget changed, it should be drawn again
+    Handled,
+    /// Entry or button `index` was chosen
+    Activated(usize),
+    /// The widget was dismissed with Escape
+    Cancelled,
+}
+
+/// Colors the widgets are drawn with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Palette {
+    /// Window background and text
+    pub window: ColorCode,
+    pub border: ColorCode,
+    pub title: ColorCode,
+    /// Selected entry of a widget without the focus
+    pub selected: ColorCode,
+    /// Selected entry of the focused widget
+    pub focused: ColorCode,
+    /// Filled part of progress bars, and menu hotkeys
+    pub highlight: ColorCode,
+}
+
+impl Palette {
+    /// Color of an entry of a list, menu or button row, depending on whether it is the selected
+    /// one and the widget has the focus
+    pub fn entry(&self, selected: bool, focused: bool) -> ColorCode {
+        if !selected {
+            self.window
+        } else if focused {
+            self.focused
+        } else {
+            self.selected
+        }
+    }
+}
+
+/// The usual setup program look, light gray on blue
+#[allow(dead_code)]
+pub const DEFAULT_PALETTE: Palette = Palette {
+    window: ColorCode::new(Color::LightGray, Color::Blue),
+    border: ColorCode::new(Color::White, Color::Blue),
+    title: ColorCode::new(Color::Yellow, Color::Blue),
+    selected: ColorCode::new(Color::Black, Color::LightGray),
+    focused: ColorCode::new(Color::Black, Color::Cyan),
+    highlight: ColorCode::new(Color::LightCyan, Color::Blue),
+};
+
+/// Something drawn in a `Surface`, which can take the keyboard focus
+pub trait Widget {
+    fn draw(&self, surface: &mut Surface, palette: &Palette, focused: bool);
+
+    /// Reacts to `key` while the widget has the focus
+    fn handle_key(&mut self, _key: Key) -> Event {
+        Event::Ignored
+    }
+
+    /// Whether the widget takes the focus, the ones only showing something don't
+    fn focusable(&self) -> bool {
+        false
+    }
+}
+
+/// A set of widgets drawn together, with the focus on one of them
+///
+/// Keys go to the focused widget first, Tab and Shift+Tab move the focus if it ignores them.
+#[allow(dead_code)]
+pub struct Form<'a> {
+    /// Drawn in order, so later widgets cover earlier ones
+    widgets: &'a mut [&'a mut dyn Widget],
+    focus: Option<usize>,
+}
+
+#[allow(dead_code)]
+impl<'a> Form<'a> {
+    /// A form focused on its first focusable widget
+    pub fn new(widgets: &'a mut [&'a mut dyn Widget]) -> Form<'a> {
+        let focus = widgets.iter().position(|widget| widget.focusable());
+        Form { widgets, focus }
+    }
+
+    /// Index of the focused widget, `None` if none can take the focus
+    pub fn focused(&self) -> Option<usize> {
+        self.focus
+    }
+
+    /// Moves the focus to widget `index`, if it can take it
+    pub fn set_focus(&mut self, index: usize) {
+        if self
+            .widgets
+            .get(index)
+            .is_some_and(|widget| widget.focusable())
+        {
+            self.focus = Some(index);
+        }
+    }
+
+    pub fn draw(&self, surface: &mut Surface, palette: &Palette) {
+        for (index, widget) in self.widgets.iter().enumerate() {
+            widget.draw(surface, palette, self.focus == Some(index));
+        }
+    }
+
+    /// Passes `key` to the focused widget, or moves the focus on Tab and Shift+Tab
+    pub fn handle_key(&mut self, key: Key) -> Event {
+        let focus = match self.focus {
+            Some(focus) => focus,
+            None => return Event::Ignored,
+        };
+        match self.widgets[focus].handle_key(key) {
+            Event::Ignored => {}
+            event => return event,
+        }
+        let count = self.widgets.len();
+        let step = match key {
+            Key::Tab => 1,
+            Key::BackTab => count - 1,
+            _ => return Event::Ignored,
+        };
+        let mut next = focus;
+        loop {
+            next = (next + step) % count;
+            if self.widgets[next].focusable() {
+                break;
+            }
+        }
+        self.focus = Some(next);
+        Event::Handled
+    }
+}
@@ -0,0 +1,112 @@
+//! Modal box with a message and a row of buttons.
+
+use super::surface::wrap;
+use super::{Border, Event, Key, Palette, Rect, Surface, Widget, Window};
+
+/// Widest a dialog gets, longer messages are wrapped
+const MAX_WIDTH: usize = 60;
+
+pub struct Dialog<'a> {
+    rect: Rect,
+    title: &'a str,
+    message: &'a str,
+    buttons: &'a [&'a str],
+    selected: usize,
+}
+
+#[allow(dead_code)]
+impl<'a> Dialog<'a> {
+    /// A dialog centered in `area`, sized to fit its contents, with the first button selected
+    pub fn new(area: Rect, title: &'a str, message: &'a str, buttons: &'a [&'a str]) -> Dialog<'a> {
+        let longest_line = message
+            .lines()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0);
+        let content = longest_line
+            .max(buttons_width(buttons))
+            .max(title.chars().count() + 2);
+        // A border and a space on each side
+        let width = (content + 4).min(MAX_WIDTH).min(area.width);
+        let lines = wrap(message, width.saturating_sub(4)).count();
+        // The message, an empty line and the buttons between the borders
+        let rect = area.centered(lines + 4, width);
+        Dialog {
+            rect,
+            title,
+            message,
+            buttons,
+            selected: 0,
+        }
+    }
+
+    /// Index of the selected button
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+}
+
+impl Widget for Dialog<'_> {
+    fn draw(&self, surface: &mut Surface, palette: &Palette, focused: bool) {
+        Window::new(self.rect, self.title, Border::Double).draw(surface, palette, false);
+        let inner = self.rect.inner();
+        let text = Rect {
+            left: inner.left + 1,
+            height: inner.height.saturating_sub(2),
+            width: inner.width.saturating_sub(2),
+            ..inner
+        };
+        surface.print_wrapped(text, self.message, palette.window);
+
+        let row = inner.row(inner.height.saturating_sub(1));
+        let mut left = row.left + row.width.saturating_sub(buttons_width(self.buttons)) / 2;
+        for (index, button) in self.buttons.iter().enumerate() {
+            let color_code = palette.entry(index == self.selected, focused);
+            let width = button.chars().count() + 4;
+            surface.fill(Rect { left, width, ..row }, ' ', color_code);
+            surface.put(row.top, left, '<', color_code);
+            surface.put(row.top, left + width - 1, '>', color_code);
+            surface.print(
+                Rect {
+                    left: left + 2,
+                    width: width - 4,
+                    ..row
+                },
+                button,
+                color_code,
+            );
+            left += width + 1;
+        }
+    }
+
+    fn handle_key(&mut self, key: Key) -> Event {
+        let count = self.buttons.len();
+        if count == 0 {
+            return match key {
+                Key::Escape => Event::Cancelled,
+                _ => Event::Ignored,
+            };
+        }
+        match key {
+            Key::Left | Key::BackTab => self.selected = (self.selected + count - 1) % count,
+            Key::Right | Key::Tab => self.selected = (self.selected + 1) % count,
+            Key::Enter => return Event::Activated(self.selected),
+            Key::Escape => return Event::Cancelled,
+            _ => return Event::Ignored,
+        }
+        Event::Handled
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+}
+
+/// Cells the row of buttons takes, each drawn as `< label >` and one space apart
+fn buttons_width(buttons: &[&str]) -> usize {
+    let labels: usize = buttons
+        .iter()
+        .map(|button| button.chars().count() + 4)
+        .sum();
+    labels + buttons.len().saturating_sub(1)
+}
@@ -0,0 +1,52 @@
+//! Line of static text.
+
+use super::{Palette, Rect, Surface, Widget};
+
+/// Where a label sits in its row
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum Align {
+    Left,
+    Center,
+    Right,
+}
+
+pub struct Label<'a> {
+    rect: Rect,
+    text: &'a str,
+    align: Align,
+}
+
+#[allow(dead_code)]
+impl<'a> Label<'a> {
+    /// A label on the first row of `rect`, cut to its width
+    pub fn new(rect: Rect, text: &'a str, align: Align) -> Label<'a> {
+        Label { rect, text, align }
+    }
+
+    pub fn set_text(&mut self, text: &'a str) {
+        self.text = text;
+    }
+}
+
+impl Widget for Label<'_> {
+    fn draw(&self, surface: &mut Surface, palette: &Palette, _focused: bool) {
+        let row = self.rect.row(0);
+        let len = self.text.chars().count().min(row.width);
+        let left = match self.align {
+            Align::Left => row.left,
+            Align::Center => row.left + (row.width - len) / 2,
+            Align::Right => row.left + row.width - len,
+        };
+        surface.fill(row, ' ', palette.window);
+        surface.print(
+            Rect {
+                left,
+                width: len,
+                ..row
+            },
+            self.text,
+            palette.window,
+        );
+    }
+}
@@ -0,0 +1,92 @@
+//! Scrollable list of entries, one of them selected.
+
+use super::{Event, Key, Palette, Rect, Surface, Widget};
+
+pub struct List<'a> {
+    rect: Rect,
+    items: &'a [&'a str],
+    selected: usize,
+    /// Index of the first entry shown
+    offset: usize,
+}
+
+#[allow(dead_code)]
+impl<'a> List<'a> {
+    /// A list showing as many of `items` as `rect` has rows, the first one selected
+    pub fn new(rect: Rect, items: &'a [&'a str]) -> List<'a> {
+        List {
+            rect,
+            items,
+            selected: 0,
+            offset: 0,
+        }
+    }
+
+    /// Index of the selected entry, meaningless if the list is empty
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: usize) {
+        self.selected = index.min(self.items.len().saturating_sub(1));
+        // Scroll just enough for the selection to be visible
+        let rows = self.rect.height.max(1);
+        if self.selected < self.offset {
+            self.offset = self.selected;
+        } else if self.selected >= self.offset + rows {
+            self.offset = self.selected + 1 - rows;
+        }
+    }
+}
+
+impl Widget for List<'_> {
+    fn draw(&self, surface: &mut Surface, palette: &Palette, focused: bool) {
+        surface.fill(self.rect, ' ', palette.window);
+        let visible = self.items.iter().enumerate().skip(self.offset);
+        for (row, (index, item)) in (0..self.rect.height).zip(visible) {
+            let line = self.rect.row(row);
+            let color_code = palette.entry(index == self.selected, focused);
+            surface.fill(line, ' ', color_code);
+            // The last column is kept for the scroll arrows
+            let text = Rect {
+                width: line.width.saturating_sub(1),
+                ..line
+            };
+            surface.print(text, item, color_code);
+        }
+        // Arrows in the top and bottom right corners tell there is more to scroll to
+        let right = self.rect.left + self.rect.width.saturating_sub(1);
+        if self.offset > 0 {
+            surface.put(self.rect.top, right, '↑', palette.border);
+        }
+        if self.offset + self.rect.height < self.items.len() && self.rect.height > 0 {
+            surface.put(
+                self.rect.top + self.rect.height - 1,
+                right,
+                '↓',
+                palette.border,
+            );
+        }
+    }
+
+    fn handle_key(&mut self, key: Key) -> Event {
+        let page = self.rect.height.max(1);
+        let last = self.items.len().saturating_sub(1);
+        let selected = match key {
+            Key::Up => self.selected.saturating_sub(1),
+            Key::Down => self.selected + 1,
+            Key::PageUp => self.selected.saturating_sub(page),
+            Key::PageDown => self.selected + page,
+            Key::Home => 0,
+            Key::End => last,
+            Key::Enter if !self.items.is_empty() => return Event::Activated(self.selected),
+            _ => return Event::Ignored,
+        };
+        self.select(selected);
+        Event::Handled
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+}
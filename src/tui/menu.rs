@@ -0,0 +1,112 @@
+//! Pop-up menu of commands, chosen with the arrows and Enter or with their hotkey.
+
+use super::{Border, Event, Key, Palette, Rect, Surface, Widget};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MenuItem<'a> {
+    pub label: &'a str,
+    /// Key choosing the entry directly, highlighted in the label if it appears there
+    pub hotkey: Option<char>,
+}
+
+pub struct Menu<'a> {
+    rect: Rect,
+    items: &'a [MenuItem<'a>],
+    selected: usize,
+}
+
+#[allow(dead_code)]
+impl<'a> Menu<'a> {
+    /// A framed menu with its top left corner at `top`/`left`, sized to fit `items`
+    pub fn new(top: usize, left: usize, items: &'a [MenuItem<'a>]) -> Menu<'a> {
+        let width = items
+            .iter()
+            .map(|item| item.label.chars().count())
+            .max()
+            .unwrap_or(0);
+        // A border and a space on each side
+        let rect = Rect::new(top, left, items.len() + 2, width + 4);
+        Menu {
+            rect,
+            items,
+            selected: 0,
+        }
+    }
+
+    /// The area the menu covers, border included
+    pub fn rect(&self) -> Rect {
+        self.rect
+    }
+
+    pub fn selected(&self) -> usize {
+        self.selected
+    }
+}
+
+impl Widget for Menu<'_> {
+    fn draw(&self, surface: &mut Surface, palette: &Palette, focused: bool) {
+        surface.fill(self.rect, ' ', palette.window);
+        surface.frame(self.rect, Border::Single, palette.border);
+        let inner = self.rect.inner();
+        for (row, item) in self.items.iter().enumerate() {
+            let line = inner.row(row);
+            let selected = row == self.selected;
+            let color_code = palette.entry(selected, focused);
+            // The hotkey only stands out on the entries that aren't highlighted already
+            let hotkey_color_code = if selected {
+                color_code
+            } else {
+                palette.highlight
+            };
+            surface.fill(line, ' ', color_code);
+            let text = Rect {
+                left: line.left + 1,
+                width: line.width.saturating_sub(2),
+                ..line
+            };
+            surface.print(text, item.label, color_code);
+            let hotkey = item.hotkey.and_then(|key| {
+                let mut chars = item.label.chars().enumerate();
+                chars.find(|(_, c)| c.eq_ignore_ascii_case(&key))
+            });
+            if let Some((col, c)) = hotkey.filter(|&(col, _)| col < text.width) {
+                surface.put(text.top, text.left + col, c, hotkey_color_code);
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: Key) -> Event {
+        let count = self.items.len();
+        if count == 0 {
+            return Event::Ignored;
+        }
+        match key {
+            // The selection wraps around at both ends
+            Key::Up => self.selected = (self.selected + count - 1) % count,
+            Key::Down => self.selected = (self.selected + 1) % count,
+            Key::Home => self.selected = 0,
+            Key::End => self.selected = count - 1,
+            Key::Enter => return Event::Activated(self.selected),
+            Key::Escape => return Event::Cancelled,
+            Key::Char(c) => {
+                let hotkey = self
+                    .items
+                    .iter()
+                    .position(|item| item.hotkey.is_some_and(|key| key.eq_ignore_ascii_case(&c)));
+                return match hotkey {
+                    Some(index) => {
+                        self.selected = index;
+                        Event::Activated(index)
+                    }
+                    None => Event::Ignored,
+                };
+            }
+            _ => return Event::Ignored,
+        }
+        Event::Handled
+    }
+
+    fn focusable(&self) -> bool {
+        true
+    }
+}
@@ -0,0 +1,103 @@
+//! Horizontal bar filling up as some work progresses.
+
+use core::fmt::{self, Write};
+
+use super::{Palette, Rect, Surface, Widget};
+
+/// Cells the percentage takes on the right of the bar, as in ` 100%`
+const PERCENTAGE_WIDTH: usize = 5;
+
+pub struct ProgressBar {
+    rect: Rect,
+    value: u64,
+    max: u64,
+}
+
+#[allow(dead_code)]
+impl ProgressBar {
+    /// An empty bar on the first row of `rect`, full once the value reaches `max`
+    pub fn new(rect: Rect, max: u64) -> ProgressBar {
+        ProgressBar {
+            rect,
+            value: 0,
+            max,
+        }
+    }
+
+    /// Sets the progress, values above the maximum count as the maximum
+    pub fn set_value(&mut self, value: u64) {
+        self.value = value.min(self.max);
+    }
+
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
+    /// Progress from 0 to `scale`, rounded down
+    fn scaled(&self, scale: usize) -> usize {
+        if self.max == 0 {
+            return scale;
+        }
+        (self.value as u128 * scale as u128 / self.max as u128) as usize
+    }
+}
+
+impl Widget for ProgressBar {
+    fn draw(&self, surface: &mut Surface, palette: &Palette, _focused: bool) {
+        let row = self.rect.row(0);
+        let width = row.width.saturating_sub(PERCENTAGE_WIDTH);
+        let filled = self.scaled(width);
+        surface.fill(
+            Rect {
+                width: filled,
+                ..row
+            },
+            '█',
+            palette.highlight,
+        );
+        surface.fill(
+            Rect {
+                left: row.left + filled,
+                width: width - filled,
+                ..row
+            },
+            '░',
+            palette.window,
+        );
+
+        let mut percentage = Percentage::default();
+        let _ = write!(percentage, "{:>4}%", self.scaled(100));
+        let percentage_rect = Rect {
+            left: row.left + width,
+            width: row.width - width,
+            ..row
+        };
+        surface.fill(percentage_rect, ' ', palette.window);
+        surface.print(percentage_rect, percentage.as_str(), palette.window);
+    }
+}
+
+/// Room to format the percentage without allocating
+#[derive(Default)]
+struct Percentage {
+    bytes: [u8; PERCENTAGE_WIDTH],
+    len: usize,
+}
+
+impl Percentage {
+    fn as_str(&self) -> &str {
+        core::str::from_utf8(&self.bytes[..self.len]).unwrap_or("")
+    }
+}
+
+impl fmt::Write for Percentage {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let end = self.len + s.len();
+        self.bytes
+            .get_mut(self.len..end)
+            .ok_or(fmt::Error)?
+            .copy_from_slice(s.as_bytes());
+        self.len = end;
+        Ok(())
+    }
+}
@@ -0,0 +1,221 @@
+//! Off-screen grid of cells the widgets draw into, and the rectangles they are laid out in.
+
+use crate::vga_buffer::{ColorCode, ScreenChar, Writer, MAX_COLUMNS, MAX_ROWS};
+
+/// An area of a `Surface`, in cells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rect {
+    pub top: usize,
+    pub left: usize,
+    pub height: usize,
+    pub width: usize,
+}
+
+#[allow(dead_code)]
+impl Rect {
+    pub const fn new(top: usize, left: usize, height: usize, width: usize) -> Rect {
+        Rect {
+            top,
+            left,
+            height,
+            width,
+        }
+    }
+
+    /// The area inside a one cell border
+    pub fn inner(self) -> Rect {
+        Rect {
+            top: self.top + 1,
+            left: self.left + 1,
+            height: self.height.saturating_sub(2),
+            width: self.width.saturating_sub(2),
+        }
+    }
+
+    /// A `height` by `width` area in the middle of this one, shrunk to fit if needed
+    pub fn centered(self, height: usize, width: usize) -> Rect {
+        let height = height.min(self.height);
+        let width = width.min(self.width);
+        Rect {
+            top: self.top + (self.height - height) / 2,
+            left: self.left + (self.width - width) / 2,
+            height,
+            width,
+        }
+    }
+
+    /// Row `row` of the area, counted from its top
+    pub fn row(self, row: usize) -> Rect {
+        Rect {
+            top: self.top + row,
+            left: self.left,
+            height: usize::from(row < self.height),
+            width: self.width,
+        }
+    }
+}
+
+/// Line style of `Surface::frame`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(dead_code)]
+pub enum Border {
+    Single,
+    Double,
+}
+
+impl Border {
+    /// Top left, top right, bottom left and bottom right corners, horizontal and vertical lines
+    fn chars(self) -> [char; 6] {
+        match self {
+            Border::Single => ['┌', '┐', '└', '┘', '─', '│'],
+            Border::Double => ['╔', '╗', '╚', '╝', '═', '║'],
+        }
+    }
+}
+
+/// Cells drawn off-screen, shown on a console with `present`
+///
+/// Drawing outside of the surface is clipped.
+pub struct Surface {
+    cells: [[ScreenChar; MAX_COLUMNS]; MAX_ROWS],
+    columns: usize,
+    rows: usize,
+}
+
+#[allow(dead_code)]
+impl Surface {
+    /// A surface of `columns` by `rows` cells (at most the largest text mode) cleared to `color_code`
+    pub fn new(columns: usize, rows: usize, color_code: ColorCode) -> Surface {
+        Surface {
+            cells: [[ScreenChar::from_char(' ', color_code); MAX_COLUMNS]; MAX_ROWS],
+            columns: columns.min(MAX_COLUMNS),
+            rows: rows.min(MAX_ROWS),
+        }
+    }
+
+    /// Size of the surface in cells, as `(columns, rows)`
+    pub fn size(&self) -> (usize, usize) {
+        (self.columns, self.rows)
+    }
+
+    /// The whole surface
+    pub fn area(&self) -> Rect {
+        Rect::new(0, 0, self.rows, self.columns)
+    }
+
+    pub fn put(&mut self, row: usize, col: usize, c: char, color_code: ColorCode) {
+        if row < self.rows && col < self.columns {
+            self.cells[row][col] = ScreenChar::from_char(c, color_code);
+        }
+    }
+
+    pub fn fill(&mut self, rect: Rect, c: char, color_code: ColorCode) {
+        for row in rect.top..rect.top + rect.height {
+            for col in rect.left..rect.left + rect.width {
+                self.put(row, col, c, color_code);
+            }
+        }
+    }
+
+    /// Prints `s` on the first row of `rect`, cut to its width, and returns the cells it took
+    pub fn print(&mut self, rect: Rect, s: &str, color_code: ColorCode) -> usize {
+        if rect.height == 0 {
+            return 0;
+        }
+        let mut count = 0;
+        for (col, c) in (rect.left..rect.left + rect.width).zip(s.chars()) {
+            self.put(rect.top, col, c, color_code);
+            count += 1;
+        }
+        count
+    }
+
+    /// Prints `s` in `rect`, breaking lines between words, and returns the rows it took
+    pub fn print_wrapped(&mut self, rect: Rect, s: &str, color_code: ColorCode) -> usize {
+        let mut rows = 0;
+        for (row, line) in (0..rect.height).zip(wrap(s, rect.width)) {
+            self.print(rect.row(row), line, color_code);
+            rows += 1;
+        }
+        rows
+    }
+
+    /// Draws a border along the edges of `rect`
+    pub fn frame(&mut self, rect: Rect, border: Border, color_code: ColorCode) {
+        if rect.height < 2 || rect.width < 2 {
+            return;
+        }
+        let [top_left, top_right, bottom_left, bottom_right, horizontal, vertical] = border.chars();
+        let (bottom, right) = (rect.top + rect.height - 1, rect.left + rect.width - 1);
+        for col in rect.left + 1..right {
+            self.put(rect.top, col, horizontal, color_code);
+            self.put(bottom, col, horizontal, color_code);
+        }
+        for row in rect.top + 1..bottom {
+            self.put(row, rect.left, vertical, color_code);
+            self.put(row, right, vertical, color_code);
+        }
+        self.put(rect.top, rect.left, top_left, color_code);
+        self.put(rect.top, right, top_right, color_code);
+        self.put(bottom, rect.left, bottom_left, color_code);
+        self.put(bottom, right, bottom_right, color_code);
+    }
+
+    /// Copies the surface to the top left corner of `writer`'s console
+    pub fn present(&self, writer: &mut Writer) {
+        for (row, cells) in self.cells[..self.rows].iter().enumerate() {
+            writer.write_cells_at(row, 0, &cells[..self.columns]);
+        }
+    }
+}
+
+/// Splits `s` into lines of at most `width` characters, between words where possible
+pub fn wrap(s: &str, width: usize) -> Wrap<'_> {
+    Wrap {
+        rest: s,
+        width: width.max(1),
+    }
+}
+
+/// Iterator returned by `wrap`
+pub struct Wrap<'a> {
+    rest: &'a str,
+    width: usize,
+}
+
+impl<'a> Iterator for Wrap<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<&'a str> {
+        if self.rest.is_empty() {
+            return None;
+        }
+        // Byte index after the `width` first characters, or after the line break
+        let mut end = self.rest.len();
+        let mut last_space = None;
+        for (count, (index, c)) in self.rest.char_indices().enumerate() {
+            if c == '\n' {
+                end = index;
+                break;
+            }
+            if count == self.width {
+                // A space right after a full line ends it, a word going past it is moved to the
+                // next line, unless it is as wide as the line
+                end = if c == ' ' {
+                    index
+                } else {
+                    last_space.unwrap_or(index)
+                };
+                break;
+            }
+            if c == ' ' {
+                last_space = Some(index);
+            }
+        }
+        let line = &self.rest[..end];
+        let rest = &self.rest[end..];
+        // The space or line break the line ended at isn't printed
+        self.rest = rest.strip_prefix(|c| c == ' ' || c == '\n').unwrap_or(rest);
+        Some(line.trim_end_matches(' '))
+    }
+}
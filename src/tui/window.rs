@@ -0,0 +1,50 @@
+//! Framed window with a title, the background the other widgets are laid out on.
+
+use super::{Border, Palette, Rect, Surface, Widget};
+
+pub struct Window<'a> {
+    rect: Rect,
+    title: &'a str,
+    border: Border,
+}
+
+#[allow(dead_code)]
+impl<'a> Window<'a> {
+    /// A window covering `rect`, border included, with `title` centered in its top border
+    pub fn new(rect: Rect, title: &'a str, border: Border) -> Window<'a> {
+        Window {
+            rect,
+            title,
+            border,
+        }
+    }
+
+    /// The area inside the border, where the window contents go
+    pub fn inner(&self) -> Rect {
+        self.rect.inner()
+    }
+}
+
+impl Widget for Window<'_> {
+    fn draw(&self, surface: &mut Surface, palette: &Palette, _focused: bool) {
+        surface.fill(self.rect, ' ', palette.window);
+        surface.frame(self.rect, self.border, palette.border);
+        // The corners and a space on each side of the title stay visible
+        let room = self.rect.width.saturating_sub(4);
+        if self.title.is_empty() || room == 0 {
+            return;
+        }
+        let len = self.title.chars().count().min(room);
+        let left = self.rect.left + 1 + (self.rect.width - 2 - (len + 2)) / 2;
+        surface.fill(
+            Rect::new(self.rect.top, left, 1, len + 2),
+            ' ',
+            palette.title,
+        );
+        surface.print(
+            Rect::new(self.rect.top, left + 1, 1, len),
+            self.title,
+            palette.title,
+        );
+    }
+}
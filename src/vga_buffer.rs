@@ -1,14 +1,186 @@
-use core::fmt;
+use core::sync::atomic::{AtomicBool, Ordering};
+use core::{array, fmt, ptr};
 use lazy_static::lazy_static;
 use spin::Mutex;
 use volatile::Volatile;
 
+use crate::pc_speaker;
+use crate::psf;
+use crate::console::Console;
+use crate::vga_graphics;
+use crate::vga_registers;
+
+use self::ansi::{Action, CsiSequence, GraphicRendition};
+use self::font::FontError;
+use self::scrollback::{Line, Scrollback, SCROLLBACK_LINES};
+use self::shadow::ShadowBuffer;
+
+pub mod ansi;
+#[cfg(feature = "bench")]
+pub mod bench;
+pub mod capture;
+mod cp437;
+pub mod cursor;
+pub mod emergency;
+pub mod font;
+mod mode;
+mod region;
+mod scrollback;
+mod shadow;
+pub mod theme;
+
+pub use self::mode::{TextMode, MAX_COLUMNS, MAX_ROWS};
+pub use self::region::Region;
+
+/// Number of virtual consoles
+pub const CONSOLE_COUNT: usize = 4;
+
 lazy_static! {
-    pub static ref WRITER: Mutex<Writer> = Mutex::new(Writer {
-        column_position: 0,
-        color_code: ColorCode::new(Color::Yellow, Color::Black),
-        buffer: unsafe { &mut *(0xb8000 as *mut Buffer) },
-    });
+    /// The virtual consoles, each drawing into its own shadow buffer which is flushed to VGA memory while it is active
+    pub static ref CONSOLES: [Mutex<Writer>; CONSOLE_COUNT] = {
+        let consoles: [Mutex<Writer>; CONSOLE_COUNT] = array::from_fn(|index| {
+            // Each console gets its own slot of the static memory, and the lazy initialization runs only once
+            let memory = unsafe { &mut *ptr::addr_of_mut!(CONSOLE_MEMORY[index]) };
+            Mutex::new(Writer::new(memory))
+        });
+        consoles[0].lock().activate(unsafe { &mut *(0xb8000 as *mut Buffer) });
+        apply_blink_mode();
+        CONSOLES_READY.store(true, Ordering::Release);
+        consoles
+    };
+
+    /// The console receiving the kernel output of `print!`
+    pub static ref WRITER: &'static Mutex<Writer> = &CONSOLES[0];
+}
+
+/// Set once `CONSOLES` is initialized, before that dereferencing it could wait for the initialization
+static CONSOLES_READY: AtomicBool = AtomicBool::new(false);
+
+/// Index of the console currently shown in VGA memory
+static ACTIVE_CONSOLE: Mutex<usize> = Mutex::new(0);
+
+/// The text mode all consoles are laid out for
+static TEXT_MODE: Mutex<TextMode> = Mutex::new(TextMode::Text80x25);
+
+/// Whether bit 7 of the attributes makes characters blink, rather than select a bright background
+static BLINK_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Attribute mode control register of the attribute controller
+const ATTRIBUTE_MODE_CONTROL: u8 = 0x10;
+/// Bit of the attribute mode control register giving attribute bit 7 the meaning "blink"
+const BLINK_ENABLE: u8 = 1 << 3;
+
+/// Memory owned by one virtual console, kept in a static so the writers themselves stay small
+struct ConsoleMemory {
+    screen: ShadowBuffer,
+    live_screen: [Line; MAX_ROWS],
+    scrollback: [Line; SCROLLBACK_LINES],
+}
+
+/// Zeroed cells are blank, so the whole console memory can live in .bss
+///
+/// They are black on black though, which would hide the cursor, so `Writer::new` fills the memory
+/// with blanks in the console colors before using it.
+const EMPTY_CELL: ScreenChar = ScreenChar {
+    ascii_char: 0,
+    color_code: ColorCode(0),
+};
+const EMPTY_CONSOLE_MEMORY: ConsoleMemory = ConsoleMemory {
+    screen: ShadowBuffer::new(),
+    live_screen: [[EMPTY_CELL; MAX_COLUMNS]; MAX_ROWS],
+    scrollback: [[EMPTY_CELL; MAX_COLUMNS]; SCROLLBACK_LINES],
+};
+static mut CONSOLE_MEMORY: [ConsoleMemory; CONSOLE_COUNT] = [EMPTY_CONSOLE_MEMORY; CONSOLE_COUNT];
+
+/// Returns the index of the console currently on screen
+#[allow(dead_code)]
+pub fn active_console() -> usize {
+    *ACTIVE_CONSOLE.lock()
+}
+
+/// Brings console `index` on screen, the previously active one keeps running off-screen
+///
+/// Out of range indices are ignored.
+#[allow(dead_code)]
+pub fn switch_console(index: usize) {
+    if index >= CONSOLE_COUNT {
+        return;
+    }
+    let mut active = ACTIVE_CONSOLE.lock();
+    if *active == index {
+        return;
+    }
+    let vga = CONSOLES[*active].lock().deactivate();
+    CONSOLES[index].lock().activate(vga);
+    *active = index;
+}
+
+/// Copies the whole active console to VGA memory again, e.g. after leaving a graphics mode
+#[allow(dead_code)]
+pub fn redraw_active_console() {
+    let active = ACTIVE_CONSOLE.lock();
+    CONSOLES[*active].lock().redraw();
+}
+
+/// Returns the current text mode
+pub fn text_mode() -> TextMode {
+    *TEXT_MODE.lock()
+}
+
+/// Switches the screen to `mode` and lays all consoles out for it
+///
+/// The 8x8 font of the 50 and 60 row modes replaces the current font, going back to 16 scanline
+/// characters brings back the font the BIOS set up (see `font::restore_default`), so a font loaded
+/// before has to be loaded again. Each console keeps the content that still fits, scrolling it up
+/// if its writing position would fall off the bottom.
+#[allow(dead_code)]
+pub fn set_text_mode(mode: TextMode) -> Result<(), FontError> {
+    if vga_graphics::current_mode().is_some() {
+        return Err(FontError::NotInTextMode);
+    }
+    let previous = text_mode();
+    vga_registers::set_mode_registers(mode.registers());
+    if mode.char_height() != previous.char_height() {
+        if mode.char_height() == 8 {
+            font::load_psf(psf::FONT_8X8)?;
+        } else {
+            font::restore_default()?;
+        }
+    }
+    *TEXT_MODE.lock() = mode;
+    apply_blink_mode();
+
+    for console in CONSOLES.iter() {
+        console.lock().resize(mode.columns(), mode.rows());
+    }
+    Ok(())
+}
+
+/// Chooses what bit 7 of the attributes does: blinking if `enabled`, else the bright half of the
+/// background colors
+///
+/// Blinking is off from boot on so that all 16 `Color`s work as backgrounds, see
+/// `ColorCode::blinking` for the blinking colors.
+#[allow(dead_code)]
+pub fn set_blink_enabled(enabled: bool) {
+    BLINK_ENABLED.store(enabled, Ordering::Relaxed);
+    apply_blink_mode();
+}
+
+#[allow(dead_code)]
+pub fn blink_enabled() -> bool {
+    BLINK_ENABLED.load(Ordering::Relaxed)
+}
+
+/// Programs the blink setting into the attribute controller, whose mode tables reset it
+pub(crate) fn apply_blink_mode() {
+    let mode_control = vga_registers::read_attribute(ATTRIBUTE_MODE_CONTROL);
+    let mode_control = if blink_enabled() {
+        mode_control | BLINK_ENABLE
+    } else {
+        mode_control & !BLINK_ENABLE
+    };
+    vga_registers::write_attribute(ATTRIBUTE_MODE_CONTROL, mode_control);
 }
 
 /// The standard color palette in VGA text mode
@@ -34,101 +206,723 @@ pub enum Color {
     White = 15
 }
 
+/// Bit of the background nibble meaning either "blink" or "bright", see `set_blink_enabled`
+const BLINK_BIT: u8 = 0x08;
+
 /// Implementation of a full color code for characters (bg + fg)
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(transparent)]
-struct ColorCode(u8);
+pub struct ColorCode(u8);
 
 impl ColorCode {
-    fn new(foreground: Color, background: Color) -> ColorCode {
+    /// Any of the 16 colors as background, as long as blinking is disabled (the default)
+    ///
+    /// With blinking enabled the bright backgrounds blink in their dark variant instead.
+    pub const fn new(foreground: Color, background: Color) -> ColorCode {
         ColorCode((background as u8) << 4 | (foreground as u8))
     }
+
+    /// Blinking characters, for when blinking is enabled with `set_blink_enabled`
+    ///
+    /// Only the 8 dark colors are possible backgrounds, the brightness bit is used for the blinking.
+    /// While blinking is disabled the characters show on the bright variant of `background`.
+    #[allow(dead_code)]
+    pub fn blinking(foreground: Color, background: Color) -> ColorCode {
+        ColorCode::from_nibbles(foreground as u8, background as u8 | BLINK_BIT)
+    }
+
+    /// Whether the blink bit is set, it only makes the characters blink while blinking is enabled
+    #[allow(dead_code)]
+    pub fn is_blinking(self) -> bool {
+        self.background() & BLINK_BIT != 0
+    }
+
+    /// Builds a color code from raw 4-bit palette indices
+    pub(crate) fn from_nibbles(foreground: u8, background: u8) -> ColorCode {
+        ColorCode((background & 0x0f) << 4 | (foreground & 0x0f))
+    }
+
+    pub(crate) fn foreground(self) -> u8 {
+        self.0 & 0x0f
+    }
+
+    pub(crate) fn background(self) -> u8 {
+        self.0 >> 4
+    }
 }
 
 /// Each character on the screen is represented by its ascii representation (the char itself) and its color
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 #[repr(C)]
-struct ScreenChar {
+pub struct ScreenChar {
     ascii_char: u8,
     color_code: ColorCode
 }
 
-/// height represents line and width columns of the text buffer
-const BUFFER_HEIGHT: usize = 25;
-const BUFFER_WIDTH: usize = 80;
+#[allow(dead_code)]
+impl ScreenChar {
+    /// A cell showing glyph `glyph` of the loaded font, which can be a custom one (see `font`)
+    pub fn new(glyph: u8, color_code: ColorCode) -> ScreenChar {
+        ScreenChar {
+            ascii_char: glyph,
+            color_code,
+        }
+    }
+
+    /// A cell showing `c`, or `■` if code page 437 has no glyph for it
+    pub fn from_char(c: char, color_code: ColorCode) -> ScreenChar {
+        ScreenChar::new(cp437::from_char(c).unwrap_or(0xfe), color_code)
+    }
+
+    /// The glyph index stored in the cell
+    pub fn glyph(self) -> u8 {
+        self.ascii_char
+    }
+
+    pub fn color_code(self) -> ColorCode {
+        self.color_code
+    }
+
+    /// An empty cell drawn with `color_code`
+    fn blank(color_code: ColorCode) -> ScreenChar {
+        ScreenChar {
+            ascii_char: b' ',
+            color_code,
+        }
+    }
+}
+
 
-/// Implementation of the text buffer per se
+/// Columns between two tab stops unless changed with `Writer::set_tab_width`
+pub const DEFAULT_TAB_WIDTH: usize = 8;
+
+/// Implementation of the text buffer per se, only ever backed by VGA memory
+///
+/// The rows follow each other without gaps, so where a cell lives depends on the width of the
+/// current text mode: `row * columns + column`.
 #[repr(transparent)]
 struct Buffer {
-    chars: [[Volatile<ScreenChar>; BUFFER_WIDTH]; BUFFER_HEIGHT]
+    chars: [Volatile<ScreenChar>; MAX_COLUMNS * MAX_ROWS]
+}
+
+/// Cursor position and attributes stored by `ESC 7` / `CSI s`
+#[derive(Debug, Clone, Copy)]
+struct SavedCursor {
+    row: usize,
+    column: usize,
+    color_code: ColorCode,
+    rendition: GraphicRendition,
 }
 
 /// The writer type allows writing to an underlying 'text buffer' that wraps at max usize
+///
+/// Output starts in the top left corner and fills the screen downwards, scrolling only once the
+/// last row is full. The scrolling can be confined to a band of rows (`set_scroll_region`), leaving
+/// the rows around it free for fixed content drawn through `Region`s.
+///
+/// Strings written through it may contain ANSI/VT100 escape sequences (SGR colors, cursor
+/// movement, erase and save/restore cursor), which are interpreted instead of printed.
+///
+/// Lines scrolled off the top are kept in a scrollback ring; the visible window can be moved
+/// back with `scroll_up`/`page_up` and returns to the live output as soon as something is written
+/// to the scrolling area.
+///
+/// Each writer is one virtual console drawing into its own shadow buffer in RAM. The rows it
+/// changed are copied to VGA memory by `flush` while it is the active console, which every
+/// public method does before returning.
 pub struct Writer {
+    /// Size of the screen in the current text mode
+    width: usize,
+    height: usize,
     column_position: usize,
+    row_position: usize,
     color_code: ColorCode,
-    buffer: &'static mut Buffer
+    /// Color code restored by `SGR 0` and `ESC c`
+    default_color_code: ColorCode,
+    rendition: GraphicRendition,
+    saved_cursor: Option<SavedCursor>,
+    tab_width: usize,
+    /// First and last row (inclusive) of the scrolling area, rows outside of it never move
+    scroll_top: usize,
+    scroll_bottom: usize,
+    parser: ansi::Parser,
+    scrollback: Scrollback,
+    /// How many lines the view is scrolled back, 0 means the live screen is shown
+    view_offset: usize,
+    /// Copy of the live screen, taken while the view is scrolled back
+    live_screen: &'static mut [Line; MAX_ROWS],
+    /// Whether `CSI ? 25 l` hid the cursor, applied to the hardware when the console is active
+    cursor_visible: bool,
+    /// VGA memory, only while this is the active console
+    vga: Option<&'static mut Buffer>,
+    buffer: &'static mut ShadowBuffer
 }
 
 impl Writer {
+    /// Creates an inactive console drawing into `memory`
+    fn new(memory: &'static mut ConsoleMemory) -> Writer {
+        let color_code = theme::current().color_code();
+        let blank = ScreenChar::blank(color_code);
+        for row in 0..MAX_ROWS {
+            memory.screen.fill(row, 0, MAX_COLUMNS, blank);
+        }
+        for line in memory.live_screen.iter_mut().chain(memory.scrollback.iter_mut()) {
+            *line = [blank; MAX_COLUMNS];
+        }
+        let mode = TextMode::Text80x25;
+        memory.screen.set_size(mode.columns(), mode.rows());
+        Writer {
+            width: mode.columns(),
+            height: mode.rows(),
+            column_position: 0,
+            row_position: 0,
+            color_code,
+            default_color_code: color_code,
+            rendition: GraphicRendition::new(),
+            saved_cursor: None,
+            tab_width: DEFAULT_TAB_WIDTH,
+            scroll_top: 0,
+            scroll_bottom: mode.rows() - 1,
+            parser: ansi::Parser::new(),
+            scrollback: Scrollback::new(&mut memory.scrollback),
+            view_offset: 0,
+            live_screen: &mut memory.live_screen,
+            cursor_visible: true,
+            vga: None,
+            buffer: &mut memory.screen,
+        }
+    }
+
+    /// Whether this console is the one shown in VGA memory
+    fn is_active(&self) -> bool {
+        self.vga.is_some()
+    }
+
+    /// Makes this console show up in `vga`, starting with a full copy of its screen
+    fn activate(&mut self, vga: &'static mut Buffer) {
+        self.vga = Some(vga);
+        self.redraw();
+        if self.cursor_visible {
+            cursor::show();
+        } else {
+            cursor::hide();
+        }
+        if self.view_offset == 0 {
+            self.update_cursor();
+        } else {
+            cursor::set_position(self.height, 0, self.width);
+        }
+    }
+
+    /// Lays the console out for a `width` x `height` screen, keeping what still fits
+    fn resize(&mut self, width: usize, height: usize) {
+        self.scroll_to_bottom();
+        // Keep the writing position on screen by scrolling the rows below the top of the scrolling
+        // area up, the lines pushed out go to the scrollback
+        let overflow = (self.row_position + 1).saturating_sub(height);
+        let top = self.scroll_top.min(height - 1);
+        for _ in 0..overflow {
+            self.scrollback.push(*self.buffer.row(top));
+            self.buffer.scroll_up(top, self.height - 1);
+        }
+        // Whatever was left in the buffer outside of the old screen is stale
+        let kept_rows = self.height - overflow;
+        for row in 0..height {
+            let from = if row < kept_rows { self.width.min(width) } else { 0 };
+            self.clear_cells(row, from, width);
+        }
+
+        self.width = width;
+        self.height = height;
+        self.buffer.set_size(width, height);
+        self.row_position -= overflow;
+        self.column_position = self.column_position.min(width);
+        if self.scroll_top >= height - 1 {
+            self.scroll_top = 0;
+        }
+        self.scroll_bottom = height - 1;
+        self.saved_cursor = None;
+
+        // The mode registers reset the cursor, put back this console's
+        if self.is_active() {
+            if self.cursor_visible {
+                cursor::show();
+            } else {
+                cursor::hide();
+            }
+            self.update_cursor();
+        }
+        self.flush();
+    }
+
+    /// Changes the colors restored by `SGR 0` and `ESC c`, and the current ones if they were the defaults
+    pub fn set_default_color_code(&mut self, color_code: ColorCode) {
+        if self.color_code == self.default_color_code {
+            self.color_code = color_code;
+        }
+        self.default_color_code = color_code;
+    }
+
+    /// Returns the size of the screen as `(columns, rows)`
+    #[allow(dead_code)]
+    pub fn size(&self) -> (usize, usize) {
+        (self.width, self.height)
+    }
+
+    /// Stops showing this console and hands out VGA memory, the shadow buffer keeps the content
+    fn deactivate(&mut self) -> &'static mut Buffer {
+        self.vga.take().expect("deactivating a console that isn't active")
+    }
+
+    /// Copies the rows changed since the last flush to VGA memory, if this console is on screen
+    pub fn flush(&mut self) {
+        if let Some(vga) = self.vga.as_deref_mut() {
+            self.buffer.flush(vga);
+        }
+    }
+
+    /// Copies the whole screen to VGA memory again, e.g. after something else drew over it
+    #[allow(dead_code)]
+    pub fn redraw(&mut self) {
+        self.buffer.mark_all_dirty();
+        self.flush();
+    }
+
     /// Receives a raw byte and prints it (or stores it to the text buffer)
+    ///
+    /// The supported control characters are executed, any other byte is stored as is.
+    #[allow(dead_code)]
     pub fn write_byte(&mut self, byte: u8) {
-        match byte {
-            b'\n' => self.new_line(),
-            byte => {
-                if self.column_position>= BUFFER_WIDTH {
-                    self.new_line();
-                }
+        self.scroll_to_bottom();
+        if !self.execute_control(byte) {
+            self.put_glyph(byte);
+        }
+        self.update_cursor();
+        self.flush();
+    }
+
+    /// Stores a glyph to the text buffer without moving the hardware cursor
+    fn put_glyph(&mut self, byte: u8) {
+        if self.column_position>= self.width {
+            self.new_line();
+        }
 
-                let row = BUFFER_HEIGHT - 1;
-                let col = self.column_position;
-                let color_code = self.color_code;
+        let row = self.row_position;
+        let col = self.column_position;
+        let color_code = self.color_code;
 
-                self.buffer.chars[row][col].write(ScreenChar {
-                    ascii_char: byte,
-                    color_code,
-                });
-                self.column_position += 1;
-            }
+        self.buffer.write(row, col, ScreenChar {
+            ascii_char: byte,
+            color_code,
+        });
+        self.column_position += 1;
+    }
+
+    /// Executes a control character, returns `false` if it isn't one the writer handles
+    fn execute_control(&mut self, byte: u8) -> bool {
+        match byte {
+            b'\n' => self.new_line(),
+            b'\r' => self.column_position = 0,
+            b'\t' => self.tab(),
+            // Backspace
+            0x08 => self.backspace(),
+            // Form feed
+            0x0c => self.form_feed(),
+            // Bell
+            0x07 => pc_speaker::bell(),
+            _ => return false,
         }
+        true
     }
-    /// Receives a raw UTF-8 Rust string, matches byte by byte to see if its a valid ASCII char.
+    /// Receives a raw UTF-8 Rust string and feeds it char by char through the escape sequence parser.
+    /// 
+    /// Escape sequences are executed, then for the remaining chars:
     /// 
-    /// If valid, writes char to buffer &br
+    /// If the char has a code page 437 glyph, writes that glyph to buffer &br
     /// 
     /// Else, writes 0xfe to the buffer
     pub fn write_string(&mut self, s: &str) {
-        for byte in s.bytes() {
-            match byte {
-                // Valid range of ASCII characters or newline literal
-                0x20..=0x7e | b'\n' => self.write_byte(byte),
-                // Else, writes 0xfe to buffer
-                _ => self.write_byte(0xfe),
+        self.scroll_to_bottom();
+        for c in s.chars() {
+            match self.parser.advance(c) {
+                Some(Action::Print(c)) if c.is_ascii_control() => {
+                    // Unsupported control characters have no glyph either
+                    if !self.execute_control(c as u8) {
+                        self.put_glyph(0xfe);
+                    }
+                }
+                // Printable ASCII, box-drawing, accented letters... or 0xfe if there is no glyph
+                Some(Action::Print(c)) => self.put_glyph(cp437::from_char(c).unwrap_or(0xfe)),
+                Some(Action::Csi(sequence)) => self.execute_csi(&sequence),
+                Some(Action::SaveCursor) => self.save_cursor(),
+                Some(Action::RestoreCursor) => self.restore_cursor(),
+                Some(Action::Reset) => self.reset(),
+                None => {}
             }
         }
+        self.update_cursor();
+        self.flush();
     }  
 
+    /// Returns the `(row, column)` where the next character will be written
+    #[allow(dead_code)]
+    pub fn position(&self) -> (usize, usize) {
+        (self.row_position, self.column_position)
+    }
+
+    /// Moves the writing position to `row`/`col`, clamped to the screen
+    #[allow(dead_code)]
+    pub fn set_position(&mut self, row: usize, col: usize) {
+        self.scroll_to_bottom();
+        self.row_position = row.min(self.height - 1);
+        self.column_position = col.min(self.width - 1);
+        self.update_cursor();
+    }
+
+    /// Writes `s` at `row`/`col` in `color_code`, leaving the writing position and color untouched
+    ///
+    /// The text is clipped at the end of the row instead of wrapping, it never scrolls the screen
+    /// and escape sequences are not interpreted, which makes it suitable for fixed labels.
+    #[allow(dead_code)]
+    pub fn write_at(&mut self, row: usize, col: usize, s: &str, color_code: ColorCode) {
+        if row >= self.height {
+            return;
+        }
+        self.scroll_to_bottom();
+        for (col, c) in (col..self.width).zip(s.chars()) {
+            self.buffer.write(row, col, ScreenChar::from_char(c, color_code));
+        }
+        self.flush();
+    }
+
+    /// Prints glyph `glyph` at the writing position, even if it is the index of a control character
+    ///
+    /// This is how custom glyphs uploaded with `font::set_glyph` are printed.
+    #[allow(dead_code)]
+    pub fn write_glyph(&mut self, glyph: u8) {
+        self.scroll_to_bottom();
+        self.put_glyph(glyph);
+        self.update_cursor();
+        self.flush();
+    }
+
+    /// Stores `cells` from `row`/`col` on, clipped like `write_at`
+    #[allow(dead_code)]
+    pub fn write_cells_at(&mut self, row: usize, col: usize, cells: &[ScreenChar]) {
+        if row >= self.height {
+            return;
+        }
+        self.scroll_to_bottom();
+        for (col, &cell) in (col..self.width).zip(cells) {
+            self.buffer.write(row, col, cell);
+        }
+        self.flush();
+    }
+
+    /// Sets the distance between tab stops, a width of 0 is treated as 1
+    #[allow(dead_code)]
+    pub fn set_tab_width(&mut self, width: usize) {
+        self.tab_width = width.max(1);
+    }
+
+    /// Advances to the next tab stop, or to the end of the row if there is none left
+    fn tab(&mut self) {
+        let next_stop = (self.column_position / self.tab_width + 1) * self.tab_width;
+        self.column_position = next_stop.min(self.width);
+    }
+
+    /// Moves back one cell and blanks it, going up to the end of the previous row from column 0
+    fn backspace(&mut self) {
+        if self.column_position > 0 {
+            self.column_position = self.column_position.min(self.width) - 1;
+        } else if self.row_position > 0 {
+            self.row_position -= 1;
+            self.column_position = self.width - 1;
+        } else {
+            return;
+        }
+        let (row, col) = (self.row_position, self.column_position);
+        self.clear_cells(row, col, col + 1);
+    }
+
+    /// Clears the screen and moves to the top left corner
+    fn form_feed(&mut self) {
+        for row in self.scroll_top..=self.scroll_bottom {
+            self.clear_row(row);
+        }
+        self.row_position = self.scroll_top;
+        self.column_position = 0;
+    }
+
+    /// Confines scrolling to the rows `top..=bottom` and moves to the start of that area
+    ///
+    /// Invalid ranges (empty, or past the screen) are ignored.
+    #[allow(dead_code)]
+    pub fn set_scroll_region(&mut self, top: usize, bottom: usize) {
+        if top >= bottom || bottom >= self.height {
+            return;
+        }
+        // The scrollback only makes sense for one scrolling area, start from the live screen
+        self.scroll_to_bottom();
+        self.scroll_top = top;
+        self.scroll_bottom = bottom;
+        self.row_position = top;
+        self.column_position = 0;
+        self.update_cursor();
+        self.flush();
+    }
+
+    /// Draws `s` into `region`, which lives in this console's screen
+    ///
+    /// A region outside of the scrolling area leaves a scrolled back view where it is, since those
+    /// rows show their live content anyway. One overlapping it brings the live screen back first.
+    #[allow(dead_code)]
+    pub fn write_region(&mut self, region: &mut Region, s: &str) {
+        if self.overlaps_scroll_area(region) {
+            self.scroll_to_bottom();
+        }
+        region.write_str(self.buffer, s);
+        self.flush();
+    }
+
+    /// Blanks `region` in its color, the view is kept like for `write_region`
+    #[allow(dead_code)]
+    pub fn clear_region(&mut self, region: &mut Region) {
+        if self.overlaps_scroll_area(region) {
+            self.scroll_to_bottom();
+        }
+        region.clear(self.buffer);
+        self.flush();
+    }
+
+    fn overlaps_scroll_area(&self, region: &Region) -> bool {
+        let (top, _, height, _) = region.bounds();
+        top <= self.scroll_bottom && top + height > self.scroll_top
+    }
+
+    /// Moves the hardware cursor to where the next character will be written
+    fn update_cursor(&self) {
+        if !self.is_active() {
+            return;
+        }
+        // A pending wrap leaves the column one past the edge, keep the cursor on the last cell
+        cursor::set_position(self.row_position, self.column_position.min(self.width - 1), self.width);
+    }
+
     fn new_line(&mut self) {
-        for row in 1..BUFFER_HEIGHT {
-            for col in 0..BUFFER_WIDTH {
-                let character = self.buffer.chars[row][col].read();
-                self.buffer.chars[row - 1][col].write(character);
+        self.column_position = 0;
+        // Below the scrolling area the cursor just stops at the last row
+        if self.row_position != self.scroll_bottom {
+            if self.row_position < self.height - 1 {
+                self.row_position += 1;
             }
+            return;
         }
-        self.clear_row(BUFFER_HEIGHT - 1);
-        self.column_position = 0;
+        let top = self.scroll_top;
+        self.scrollback.push(*self.buffer.row(top));
+        self.buffer.scroll_up(top, self.scroll_bottom);
+        self.clear_row(self.scroll_bottom);
     }
 
     fn clear_row(&mut self, row: usize) {
-        let blank_char = ScreenChar {
-            ascii_char: b' ',
-            color_code: self.color_code
-        };
-        for col in 0..BUFFER_WIDTH {
-            self.buffer.chars[row][col].write(blank_char);
+        self.clear_cells(row, 0, self.width);
+    }
+
+    /// Blanks the columns `from..to` of `row` with the current color
+    fn clear_cells(&mut self, row: usize, from: usize, to: usize) {
+        self.buffer.fill(row, from, to, ScreenChar::blank(self.color_code));
+    }
+
+    /// Runs a complete CSI sequence, unsupported commands are ignored
+    fn execute_csi(&mut self, sequence: &CsiSequence) {
+        // Private sequences (`CSI ? ...`) are terminal mode switches, only DECTCEM (cursor visibility) is supported
+        if sequence.private {
+            if sequence.param(0, 0) == 25 {
+                match sequence.final_byte {
+                    b'h' => self.cursor_visible = true,
+                    b'l' => self.cursor_visible = false,
+                    _ => return,
+                }
+                if self.is_active() {
+                    if self.cursor_visible {
+                        cursor::show();
+                    } else {
+                        cursor::hide();
+                    }
+                }
+            }
+            return;
+        }
+        let count = sequence.param(0, 1) as usize;
+        match sequence.final_byte {
+            // CUU / CUD / CUF / CUB: relative cursor movement, clamped to the screen
+            b'A' => self.row_position = self.row_position.saturating_sub(count),
+            b'B' => self.row_position = (self.row_position + count).min(self.height - 1),
+            b'C' => self.column_position = (self.column_position + count).min(self.width - 1),
+            b'D' => self.column_position = self.column_position.saturating_sub(count),
+            // CUP: absolute position, 1-based row;column
+            b'H' | b'f' => {
+                self.row_position = (sequence.param(0, 1) as usize - 1).min(self.height - 1);
+                self.column_position = (sequence.param(1, 1) as usize - 1).min(self.width - 1);
+            }
+            b'J' => self.erase_in_display(sequence.param(0, 0)),
+            b'K' => self.erase_in_line(sequence.param(0, 0)),
+            b'm' => self.select_graphic_rendition(sequence.params()),
+            // DECSTBM: 1-based top;bottom of the scrolling area
+            b'r' => {
+                let top = sequence.param(0, 1) as usize;
+                let bottom = sequence.param(1, self.height as u16) as usize;
+                self.set_scroll_region(top - 1, bottom - 1);
+            }
+            b's' => self.save_cursor(),
+            b'u' => self.restore_cursor(),
+            _ => {}
+        }
+    }
+
+    /// ED: 0 erases from the cursor to the end of the screen, 1 from the start to the cursor, 2 everything
+    fn erase_in_display(&mut self, mode: u16) {
+        let row = self.row_position;
+        match mode {
+            0 => {
+                self.erase_in_line(0);
+                for row in row + 1..self.height {
+                    self.clear_row(row);
+                }
+            }
+            1 => {
+                for row in 0..row {
+                    self.clear_row(row);
+                }
+                self.erase_in_line(1);
+            }
+            2 | 3 => {
+                for row in 0..self.height {
+                    self.clear_row(row);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// EL: 0 erases from the cursor to the end of the line, 1 from the start to the cursor, 2 the whole line
+    fn erase_in_line(&mut self, mode: u16) {
+        let row = self.row_position;
+        let col = self.column_position.min(self.width - 1);
+        match mode {
+            0 => self.clear_cells(row, col, self.width),
+            1 => self.clear_cells(row, 0, col + 1),
+            2 => self.clear_row(row),
+            _ => {}
+        }
+    }
+
+    /// SGR: maps the ANSI attributes onto the VGA color code
+    fn select_graphic_rendition(&mut self, params: &[u16]) {
+        self.color_code = self
+            .rendition
+            .select(params, self.color_code, self.default_color_code);
+    }
+
+    fn save_cursor(&mut self) {
+        self.saved_cursor = Some(SavedCursor {
+            row: self.row_position,
+            column: self.column_position,
+            color_code: self.color_code,
+            rendition: self.rendition,
+        });
+    }
+
+    fn restore_cursor(&mut self) {
+        if let Some(saved) = self.saved_cursor {
+            self.row_position = saved.row;
+            self.column_position = saved.column;
+            self.color_code = saved.color_code;
+            self.rendition = saved.rendition;
         }
     }
 
+    /// Moves the view `lines` further back into the scrollback history
+    #[allow(dead_code)]
+    pub fn scroll_up(&mut self, lines: usize) {
+        let offset = (self.view_offset + lines).min(self.scrollback.line_count());
+        self.set_view_offset(offset);
+        self.flush();
+    }
+
+    /// Moves the view `lines` towards the live screen
+    #[allow(dead_code)]
+    pub fn scroll_down(&mut self, lines: usize) {
+        self.set_view_offset(self.view_offset.saturating_sub(lines));
+        self.flush();
+    }
+
+    /// Scrolls back one screen (PageUp), that is the height of the scrolling area
+    #[allow(dead_code)]
+    pub fn page_up(&mut self) {
+        self.scroll_up(self.scroll_bottom - self.scroll_top + 1);
+    }
+
+    /// Scrolls forward one screen (PageDown)
+    #[allow(dead_code)]
+    pub fn page_down(&mut self) {
+        self.scroll_down(self.scroll_bottom - self.scroll_top + 1);
+    }
+
+    /// How many lines the view is scrolled back, 0 while the live screen is shown
+    #[allow(dead_code)]
+    pub fn view_offset(&self) -> usize {
+        self.view_offset
+    }
+
+    /// Snaps the view back to the live screen
+    pub fn scroll_to_bottom(&mut self) {
+        self.set_view_offset(0);
+        self.flush();
+    }
+
+    fn set_view_offset(&mut self, offset: usize) {
+        if offset == self.view_offset {
+            return;
+        }
+        // Leaving the live screen: keep it aside so it can be put back untouched
+        if self.view_offset == 0 {
+            for (row, line) in self.live_screen.iter_mut().enumerate() {
+                *line = *self.buffer.row(row);
+            }
+        }
+        self.view_offset = offset;
+        // Only the live screen has a meaningful cursor, park it past the last cell otherwise
+        if offset == 0 {
+            self.update_cursor();
+        } else if self.is_active() {
+            cursor::set_position(self.height, 0, self.width);
+        }
+
+        // History and the live scrolling area form one sequence of lines, the view starts `offset`
+        // lines before the live part. Rows outside the scrolling area keep showing their live content.
+        let history = self.scrollback.line_count();
+        let top = self.scroll_top;
+        for row in top..=self.scroll_bottom {
+            let index = history - offset + (row - top);
+            let line = match self.scrollback.get(index) {
+                Some(line) => line,
+                None => &self.live_screen[top + index - history],
+            };
+            self.buffer.set_row(row, line);
+        }
+    }
+
+    /// `ESC c`: default colors, empty screen and the cursor in the top left corner
+    fn reset(&mut self) {
+        self.color_code = self.default_color_code;
+        self.rendition = GraphicRendition::new();
+        self.saved_cursor = None;
+        self.scroll_top = 0;
+        self.scroll_bottom = self.height - 1;
+        self.form_feed();
+    }
+
 }
 
 /// Implements Rust's std library string formatting package on the writer type
@@ -139,22 +933,15 @@ impl fmt::Write for Writer {
     }
 }
 
-/// Like the `print!` macro in the standard library, but prints to the VGA text buffer.
-#[macro_export]
-macro_rules! print {
-    ($($arg:tt)*) => ($crate::vga_buffer::_print(format_args!($($arg)*)));
-}
+/// Sends `print!` output and log messages to the kernel console, `WRITER`
+pub struct VgaSink;
 
-/// Like the `println!` macro in the standard library, but prints to the VGA text buffer.
-#[macro_export]
-macro_rules! println {
-    () => ($crate::print!("\n"));
-    ($($arg:tt)*) => ($crate::print!("{}\n", format_args!($($arg)*)));
-}
+impl Console for VgaSink {
+    fn name(&self) -> &'static str {
+        "vga"
+    }
 
-/// Prints the given formatted string to the VGA text buffer through the global `WRITER` instance.
-#[doc(hidden)]
-pub fn _print(args: fmt::Arguments) {
-    use core::fmt::Write;
-    WRITER.lock().write_fmt(args).unwrap();
+    fn write_str(&self, s: &str) {
+        WRITER.lock().write_string(s);
+    }
 }
@@ -0,0 +1,298 @@
+//! A small ANSI/VT100 escape sequence parser.
+//!
+//! The parser is a char-driven state machine: the `Writer` feeds it every character of the
+//! string it is asked to print and acts on the returned `Action`s. `GraphicRendition` then turns
+//! SGR sequences into VGA color codes, for the `Writer` and the framebuffer console alike.
+
+use super::{Color, ColorCode};
+
+/// Maximum number of numeric parameters kept for a single CSI sequence, extra ones are dropped
+const MAX_PARAMS: usize = 8;
+
+/// ANSI color numbers (the `n` in SGR 30+n / 40+n) mapped onto the VGA palette
+pub const ANSI_COLORS: [Color; 8] = [
+    Color::Black,
+    Color::Red,
+    Color::Green,
+    Color::Brown,
+    Color::Blue,
+    Color::Magenta,
+    Color::Cyan,
+    Color::LightGray,
+];
+
+/// Bright variants of `ANSI_COLORS` (SGR 90+n / 100+n)
+pub const ANSI_BRIGHT_COLORS: [Color; 8] = [
+    Color::DarkGray,
+    Color::LightRed,
+    Color::LightGreen,
+    Color::Yellow,
+    Color::LightBlue,
+    Color::Pink,
+    Color::LightCyan,
+    Color::White,
+];
+
+/// The SGR parameter selecting `color` as the foreground, the reverse of the tables above
+pub fn foreground_sgr(color: Color) -> u16 {
+    if let Some(index) = ANSI_COLORS.iter().position(|&ansi| ansi == color) {
+        30 + index as u16
+    } else {
+        // Every color is in one of the two tables
+        let index = ANSI_BRIGHT_COLORS.iter().position(|&ansi| ansi == color).unwrap_or(7);
+        90 + index as u16
+    }
+}
+
+/// The SGR attributes a color code can't tell by itself
+///
+/// Bold is rendered as the bright variant of the foreground, reverse swaps the nibbles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GraphicRendition {
+    bold: bool,
+    /// Bold set the bright bit of the foreground, which was dark, so turning it off clears the bit
+    /// again, while a color that is bright by itself stays so
+    brightened: bool,
+    reverse: bool,
+}
+
+impl GraphicRendition {
+    /// No attribute set, as after `CSI 0 m`
+    pub const fn new() -> GraphicRendition {
+        GraphicRendition {
+            bold: false,
+            brightened: false,
+            reverse: false,
+        }
+    }
+
+    /// Applies the parameters of an SGR sequence to `color_code`, which was drawn with these
+    /// attributes, and returns the color code to draw with from now on
+    ///
+    /// `default` is what `0`, `39` and `49` go back to.
+    pub fn select(
+        &mut self,
+        params: &[u16],
+        color_code: ColorCode,
+        default: ColorCode,
+    ) -> ColorCode {
+        // `CSI m` is the same as `CSI 0 m`
+        let params = if params.is_empty() { &[0][..] } else { params };
+        let (mut foreground, mut background) = if self.reverse {
+            (color_code.background(), color_code.foreground())
+        } else {
+            (color_code.foreground(), color_code.background())
+        };
+
+        for &param in params {
+            match param {
+                0 => {
+                    *self = GraphicRendition::new();
+                    foreground = default.foreground();
+                    background = default.background();
+                }
+                1 => {
+                    self.bold = true;
+                    foreground = self.brighten(foreground);
+                }
+                22 => {
+                    if self.brightened {
+                        foreground &= !0x08;
+                    }
+                    self.bold = false;
+                    self.brightened = false;
+                }
+                7 => self.reverse = true,
+                27 => self.reverse = false,
+                30..=37 => {
+                    self.brightened = false;
+                    foreground = self.brighten(ANSI_COLORS[(param - 30) as usize] as u8);
+                }
+                39 => {
+                    self.brightened = false;
+                    foreground = self.brighten(default.foreground());
+                }
+                40..=47 => background = ANSI_COLORS[(param - 40) as usize] as u8,
+                49 => background = default.background(),
+                90..=97 => {
+                    self.brightened = false;
+                    foreground = ANSI_BRIGHT_COLORS[(param - 90) as usize] as u8;
+                }
+                100..=107 => background = ANSI_BRIGHT_COLORS[(param - 100) as usize] as u8,
+                _ => {}
+            }
+        }
+
+        if self.reverse {
+            ColorCode::from_nibbles(background, foreground)
+        } else {
+            ColorCode::from_nibbles(foreground, background)
+        }
+    }
+
+    /// The bright variant of a dark `foreground` while bold is on
+    fn brighten(&mut self, foreground: u8) -> u8 {
+        if self.bold && foreground & 0x08 == 0 {
+            self.brightened = true;
+            foreground | 0x08
+        } else {
+            foreground
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    /// Plain text, characters are printed as they come
+    Ground,
+    /// An ESC character was received
+    Escape,
+    /// Inside a `ESC [` control sequence, collecting parameters
+    Csi,
+}
+
+/// A complete Control Sequence Introducer sequence, e.g. `ESC [ 1 ; 31 m`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CsiSequence {
+    params: [u16; MAX_PARAMS],
+    len: usize,
+    /// Set when the sequence carried a private marker such as `?`
+    pub private: bool,
+    /// The byte that terminated the sequence and selects the command
+    pub final_byte: u8,
+}
+
+impl CsiSequence {
+    const fn empty() -> CsiSequence {
+        CsiSequence {
+            params: [0; MAX_PARAMS],
+            len: 0,
+            private: false,
+            final_byte: 0,
+        }
+    }
+
+    /// All parameters in the order they were received (missing ones are 0)
+    pub fn params(&self) -> &[u16] {
+        &self.params[..self.len.min(MAX_PARAMS)]
+    }
+
+    /// Returns the parameter at `index`, or `default` if it is missing or 0
+    pub fn param(&self, index: usize, default: u16) -> u16 {
+        match self.params().get(index) {
+            Some(&value) if value != 0 => value,
+            _ => default,
+        }
+    }
+}
+
+/// What the `Writer` should do after a character went through the parser
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    /// Print (or handle as a control character) the given character
+    Print(char),
+    /// Execute a complete CSI sequence
+    Csi(CsiSequence),
+    /// `ESC 7`: save cursor position and attributes
+    SaveCursor,
+    /// `ESC 8`: restore cursor position and attributes
+    RestoreCursor,
+    /// `ESC c`: reset the terminal to its initial state
+    Reset,
+}
+
+/// State machine turning a character stream into `Action`s
+#[derive(Debug)]
+pub struct Parser {
+    state: State,
+    sequence: CsiSequence,
+}
+
+impl Parser {
+    pub const fn new() -> Parser {
+        Parser {
+            state: State::Ground,
+            sequence: CsiSequence::empty(),
+        }
+    }
+
+    /// Feeds one character to the parser, returns `None` while a sequence is still incomplete
+    pub fn advance(&mut self, c: char) -> Option<Action> {
+        // CAN and SUB abort any sequence in progress, ESC always starts a new one
+        match c {
+            '\x18' | '\x1a' => {
+                self.state = State::Ground;
+                return None;
+            }
+            '\x1b' => {
+                self.state = State::Escape;
+                return None;
+            }
+            _ => {}
+        }
+
+        match self.state {
+            State::Ground => Some(Action::Print(c)),
+            State::Escape => {
+                self.state = State::Ground;
+                match c {
+                    '[' => {
+                        self.sequence = CsiSequence::empty();
+                        self.state = State::Csi;
+                        None
+                    }
+                    '7' => Some(Action::SaveCursor),
+                    '8' => Some(Action::RestoreCursor),
+                    'c' => Some(Action::Reset),
+                    // Unsupported escape, swallow it
+                    _ => None,
+                }
+            }
+            State::Csi => self.advance_csi(c),
+        }
+    }
+
+    fn advance_csi(&mut self, c: char) -> Option<Action> {
+        let sequence = &mut self.sequence;
+        match c {
+            '0'..='9' => {
+                if sequence.len == 0 {
+                    sequence.len = 1;
+                }
+                if let Some(param) = sequence.params.get_mut(sequence.len - 1) {
+                    *param = param.saturating_mul(10).saturating_add((c as u8 - b'0') as u16);
+                }
+                None
+            }
+            ';' => {
+                // An empty leading parameter still counts as one
+                if sequence.len == 0 {
+                    sequence.len = 1;
+                }
+                // Past the limit `len` stops one over it, so further digits have no slot and are dropped
+                if sequence.len <= MAX_PARAMS {
+                    sequence.len += 1;
+                }
+                None
+            }
+            '<'..='?' => {
+                sequence.private = true;
+                None
+            }
+            // Intermediate bytes, none of the supported commands use them
+            '\x20'..='\x2f' => None,
+            '\x40'..='\x7e' => {
+                sequence.final_byte = c as u8;
+                self.state = State::Ground;
+                Some(Action::Csi(*sequence))
+            }
+            // C0 controls inside a sequence are executed as if outside of it
+            '\x00'..='\x1f' => Some(Action::Print(c)),
+            // Anything else is malformed, drop the sequence
+            _ => {
+                self.state = State::Ground;
+                None
+            }
+        }
+    }
+}
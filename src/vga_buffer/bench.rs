@@ -0,0 +1,69 @@
+//! Measures the shadow buffer against drawing straight into VGA memory.
+//!
+//! Built with `--features bench`, the kernel runs this at boot and prints the CPU cycles both
+//! approaches need to print and scroll the same lines.
+
+use core::arch::x86_64::_rdtsc;
+
+use crate::println;
+
+use super::shadow::ShadowBuffer;
+use super::{Buffer, Color, ColorCode, ScreenChar, WRITER};
+
+/// Lines printed by each run, enough to scroll the screen many times over
+const LINES: usize = 1000;
+
+pub fn run() {
+    let character = ScreenChar {
+        ascii_char: b'#',
+        color_code: ColorCode::new(Color::LightGray, Color::Black),
+    };
+
+    let mut writer = WRITER.lock();
+    // The writer is locked for the whole run, so nothing else touches VGA memory meanwhile
+    let vga = unsafe { &mut *(0xb8000 as *mut Buffer) };
+    let (columns, rows) = writer.size();
+
+    let direct = cycles(|| {
+        for _ in 0..LINES {
+            // What `Writer::new_line` used to do: read back and rewrite every cell through VGA memory
+            for col in 0..columns {
+                vga.chars[(rows - 1) * columns + col].write(character);
+            }
+            for cell in columns..rows * columns {
+                let character = vga.chars[cell].read();
+                vga.chars[cell - columns].write(character);
+            }
+        }
+    });
+
+    let mut shadow = ShadowBuffer::new();
+    shadow.set_size(columns, rows);
+    let mut shadowed = |lines_per_flush: usize| {
+        cycles(|| {
+            for line in 0..LINES {
+                shadow.fill(rows - 1, 0, columns, character);
+                shadow.scroll_up(0, rows - 1);
+                if (line + 1).is_multiple_of(lines_per_flush) {
+                    shadow.flush(vga);
+                }
+            }
+        })
+    };
+    // One flush per line is the worst case, a `println!` of several lines flushes once for all of them
+    let per_line = shadowed(1);
+    let per_screen = shadowed(rows);
+
+    writer.redraw();
+    drop(writer);
+    println!("vga bench, {} lines:", LINES);
+    println!("  direct:                 {:>12} cycles", direct);
+    println!("  shadow, flush per line: {:>12} cycles ({}x)", per_line, direct / per_line.max(1));
+    println!("  shadow, flush per page: {:>12} cycles ({}x)", per_screen, direct / per_screen.max(1));
+}
+
+fn cycles<F: FnMut()>(mut f: F) -> u64 {
+    let start = unsafe { _rdtsc() };
+    f();
+    unsafe { _rdtsc() }.saturating_sub(start)
+}
@@ -0,0 +1,91 @@
+//! Snapshots of the text screen, written out in a format the host can compare with golden files
+//! (see `tools/screen_test.py`).
+//!
+//! A capture is a block of lines, each framed by `|` so trailing spaces survive:
+//!
+//! ```text
+//! SCREEN <name> <columns>x<rows>
+//! TEXT |<the characters of row 0>|
+//! ... one TEXT line per row
+//! ATTR |<two hex digits per cell, background then foreground>|
+//! ... one ATTR line per row
+//! END
+//! ```
+//!
+//! The characters are translated from code page 437 to Unicode. Only VGA text memory is read, so
+//! nothing is captured while the framebuffer console is shown.
+
+use core::fmt;
+use x86_64::instructions::interrupts;
+
+use super::{cp437, text_mode, Buffer, ScreenChar, EMPTY_CELL, MAX_COLUMNS, MAX_ROWS};
+use crate::serial;
+
+/// The contents of the screen at some point
+pub struct Snapshot {
+    cells: [ScreenChar; MAX_COLUMNS * MAX_ROWS],
+    columns: usize,
+    rows: usize,
+}
+
+#[allow(dead_code)]
+impl Snapshot {
+    /// Copies what is on screen now
+    pub fn take() -> Snapshot {
+        let mode = text_mode();
+        let (columns, rows) = (mode.columns(), mode.rows());
+        let buffer = unsafe { &*(0xb8000 as *const Buffer) };
+        let mut cells = [EMPTY_CELL; MAX_COLUMNS * MAX_ROWS];
+        for (cell, stored) in cells[..columns * rows].iter_mut().zip(buffer.chars.iter()) {
+            *cell = stored.read();
+        }
+        Snapshot {
+            cells,
+            columns,
+            rows,
+        }
+    }
+
+    /// Size of the captured screen, as `(columns, rows)`
+    pub fn size(&self) -> (usize, usize) {
+        (self.columns, self.rows)
+    }
+
+    /// The cell at `row`/`col`
+    pub fn cell(&self, row: usize, col: usize) -> ScreenChar {
+        self.cells[row * self.columns + col]
+    }
+
+    /// Writes the capture block under `name`, which must not contain spaces
+    pub fn write(&self, name: &str, out: &mut dyn fmt::Write) -> fmt::Result {
+        writeln!(out, "SCREEN {} {}x{}", name, self.columns, self.rows)?;
+        for row in self.rows() {
+            out.write_str("TEXT |")?;
+            for cell in row {
+                out.write_char(cp437::to_char(cell.glyph()))?;
+            }
+            writeln!(out, "|")?;
+        }
+        for row in self.rows() {
+            out.write_str("ATTR |")?;
+            for cell in row {
+                write!(out, "{:02x}", cell.color_code().0)?;
+            }
+            writeln!(out, "|")?;
+        }
+        writeln!(out, "END")
+    }
+
+    fn rows(&self) -> impl Iterator<Item = &[ScreenChar]> {
+        self.cells[..self.columns * self.rows].chunks(self.columns)
+    }
+}
+
+/// Captures the screen and sends it to the first serial port under `name`
+#[allow(dead_code)]
+pub fn to_serial(name: &str) {
+    let snapshot = Snapshot::take();
+    interrupts::without_interrupts(|| {
+        let _ = snapshot.write(name, &mut *serial::SERIAL1.lock());
+    });
+}
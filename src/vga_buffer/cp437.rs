@@ -0,0 +1,63 @@
+//! Translation from Unicode to the glyphs of code page 437, the VGA text mode character set.
+
+/// Glyphs of the control character range 0x00..=0x1f (0x00 is an empty cell, not a glyph)
+const LOW_GLYPHS: [char; 32] = [
+    '\0', '☺', '☻', '♥', '♦', '♣', '♠', '•', '◘', '○', '◙', '♂', '♀', '♪', '♫', '☼',
+    '►', '◄', '↕', '‼', '¶', '§', '▬', '↨', '↑', '↓', '→', '←', '∟', '↔', '▲', '▼',
+];
+
+/// Glyph at 0x7f, where ASCII has DEL
+const HOUSE: char = '⌂';
+
+/// Glyphs of the upper half 0x80..=0xff
+const HIGH_GLYPHS: [char; 128] = [
+    'Ç', 'ü', 'é', 'â', 'ä', 'à', 'å', 'ç', 'ê', 'ë', 'è', 'ï', 'î', 'ì', 'Ä', 'Å',
+    'É', 'æ', 'Æ', 'ô', 'ö', 'ò', 'û', 'ù', 'ÿ', 'Ö', 'Ü', '¢', '£', '¥', '₧', 'ƒ',
+    'á', 'í', 'ó', 'ú', 'ñ', 'Ñ', 'ª', 'º', '¿', '⌐', '¬', '½', '¼', '¡', '«', '»',
+    '░', '▒', '▓', '│', '┤', '╡', '╢', '╖', '╕', '╣', '║', '╗', '╝', '╜', '╛', '┐',
+    '└', '┴', '┬', '├', '─', '┼', '╞', '╟', '╚', '╔', '╩', '╦', '╠', '═', '╬', '╧',
+    '╨', '╤', '╥', '╙', '╘', '╒', '╓', '╫', '╪', '┘', '┌', '█', '▄', '▌', '▐', '▀',
+    'α', 'ß', 'Γ', 'π', 'Σ', 'σ', 'µ', 'τ', 'Φ', 'Θ', 'Ω', 'δ', '∞', 'φ', 'ε', '∩',
+    '≡', '±', '≥', '≤', '⌠', '⌡', '÷', '≈', '°', '∙', '·', '√', 'ⁿ', '²', '■', '\u{a0}',
+];
+
+/// Look-alike characters that have no glyph of their own but share one in CP437
+const ALIASES: [(char, u8); 4] = [
+    // Greek small beta and mu are drawn with the sharp s and micro sign glyphs
+    ('β', 0xe1),
+    ('μ', 0xe6),
+    // N-ary summation and the ohm sign look like the Greek capitals
+    ('∑', 0xe4),
+    ('\u{2126}', 0xea),
+];
+
+/// Returns the CP437 code of the glyph for `c`, or `None` if the character set has none
+///
+/// Printable ASCII maps onto itself, control characters have no glyph.
+pub fn from_char(c: char) -> Option<u8> {
+    match c {
+        ' '..='~' => Some(c as u8),
+        HOUSE => Some(0x7f),
+        c if c.is_ascii() => None,
+        c => LOW_GLYPHS
+            .iter()
+            .position(|&glyph| glyph == c)
+            .map(|index| index as u8)
+            .or_else(|| HIGH_GLYPHS.iter().position(|&glyph| glyph == c).map(|index| 0x80 + index as u8))
+            .or_else(|| ALIASES.iter().find(|&&(alias, _)| alias == c).map(|&(_, code)| code)),
+    }
+}
+
+/// Returns the character glyph `glyph` shows, the inverse of `from_char`
+///
+/// The empty cell 0x00 reads as a space.
+#[allow(dead_code)]
+pub fn to_char(glyph: u8) -> char {
+    match glyph {
+        0x00 => ' ',
+        0x01..=0x1f => LOW_GLYPHS[glyph as usize],
+        0x7f => HOUSE,
+        0x80..=0xff => HIGH_GLYPHS[glyph as usize - 0x80],
+        _ => glyph as char,
+    }
+}
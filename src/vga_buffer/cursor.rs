@@ -0,0 +1,44 @@
+//! Hardware text cursor, driven through the CRT controller registers.
+
+use crate::vga_registers::{read_crtc, write_crtc};
+
+const CURSOR_START: u8 = 0x0a;
+const CURSOR_END: u8 = 0x0b;
+const CURSOR_LOCATION_HIGH: u8 = 0x0e;
+const CURSOR_LOCATION_LOW: u8 = 0x0f;
+
+/// Bit of the cursor start register that turns the cursor off
+const CURSOR_DISABLE: u8 = 1 << 5;
+/// The scanline fields are 5 bits wide
+const SCANLINE_MASK: u8 = 0x1f;
+
+/// Moves the blinking cursor to the given cell of a screen `columns` wide
+pub fn set_position(row: usize, col: usize, columns: usize) {
+    let position = (row * columns + col) as u16;
+    write_crtc(CURSOR_LOCATION_LOW, (position & 0xff) as u8);
+    write_crtc(CURSOR_LOCATION_HIGH, (position >> 8) as u8);
+}
+
+/// Turns the cursor off without touching its shape
+pub fn hide() {
+    let start = read_crtc(CURSOR_START);
+    write_crtc(CURSOR_START, start | CURSOR_DISABLE);
+}
+
+/// Turns the cursor back on with its current shape
+pub fn show() {
+    let start = read_crtc(CURSOR_START);
+    write_crtc(CURSOR_START, start & !CURSOR_DISABLE);
+}
+
+/// Sets the cursor shape as the range of scanlines `start..=end` within a character cell
+///
+/// With the default 8x16 font, `(14, 15)` is the usual underline and `(0, 15)` a full block.
+#[allow(dead_code)]
+pub fn set_shape(start: u8, end: u8) {
+    // Keep the disable bit (and the reserved bits) as they are
+    let start_register = read_crtc(CURSOR_START) & !SCANLINE_MASK;
+    write_crtc(CURSOR_START, start_register | (start & SCANLINE_MASK));
+    let end_register = read_crtc(CURSOR_END) & !SCANLINE_MASK;
+    write_crtc(CURSOR_END, end_register | (end & SCANLINE_MASK));
+}
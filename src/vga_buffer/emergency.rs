@@ -0,0 +1,159 @@
+//! Output paths for the panic handler, which must reach the screen whatever state the consoles are in.
+//!
+//! A panic can hit while the code printing holds the lock of a console, which it will never
+//! release: `print` breaks such locks instead of waiting for them. When even that is unsafe (the
+//! consoles are still being set up, or printing itself panicked), `raw_print` writes straight to
+//! VGA memory without touching any of the console state. `take_over_screen` does the same for a
+//! whole screen, for the panic report.
+
+use core::fmt;
+use core::ptr;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use spin::{Mutex, MutexGuard};
+
+use crate::fb_console::{self, FramebufferConsole};
+
+use super::{cursor, Color, ColorCode, ACTIVE_CONSOLE, CONSOLES, CONSOLES_READY, TEXT_MODE};
+
+/// Prints to whatever console is on screen, taking over its lock if it is held
+///
+/// Interrupts should be disabled first, nothing else must run while the locks are broken.
+#[allow(dead_code)]
+pub fn print(args: fmt::Arguments) {
+    use core::fmt::Write;
+    if let Some(console) = steal(&fb_console::CONSOLE).as_mut() {
+        let _ = console.write_fmt(args);
+        return;
+    }
+    if !CONSOLES_READY.load(Ordering::Acquire) {
+        raw_print(args);
+        return;
+    }
+    let active = *steal(&ACTIVE_CONSOLE);
+    let _ = steal(&CONSOLES[active]).write_fmt(args);
+}
+
+/// Writes white on red directly to VGA memory, from the top left corner on
+///
+/// Only the text mode size is read from the console state, and only if its lock is free.
+pub fn raw_print(args: fmt::Arguments) {
+    let _ = fmt::write(
+        &mut RawWriter::new(ColorCode::new(Color::White, Color::Red)),
+        args,
+    );
+}
+
+/// Clears the screen to `foreground` on `background` and returns a writer printing on it from the
+/// top left corner, breaking locks like `print`
+///
+/// In text mode the writer goes straight to VGA memory like `raw_print`, so the consoles are left
+/// as they were. Interrupts should be disabled first.
+pub fn take_over_screen(foreground: Color, background: Color) -> ScreenWriter {
+    let mut framebuffer = steal(&fb_console::CONSOLE);
+    if let Some(console) = framebuffer.as_mut() {
+        console.set_color(foreground, background);
+        console.clear();
+        return ScreenWriter::Framebuffer(framebuffer);
+    }
+    drop(framebuffer);
+    let writer = RawWriter::new(ColorCode::new(foreground, background));
+    let vga = 0xb8000 as *mut u16;
+    let blank = (writer.color.0 as u16) << 8 | b' ' as u16;
+    for position in 0..writer.columns * writer.rows {
+        unsafe { ptr::write_volatile(vga.add(position), blank) };
+    }
+    RAW_POSITION.store(0, Ordering::Relaxed);
+    cursor::hide();
+    ScreenWriter::Text(writer)
+}
+
+/// Output of `take_over_screen`, on whichever console it found
+pub enum ScreenWriter {
+    Framebuffer(MutexGuard<'static, Option<FramebufferConsole>>),
+    Text(RawWriter),
+}
+
+impl ScreenWriter {
+    /// Size of the screen in characters, as `(columns, rows)`
+    pub fn size(&self) -> (usize, usize) {
+        match self {
+            ScreenWriter::Framebuffer(console) => {
+                console.as_ref().map_or((80, 25), |console| console.size())
+            }
+            ScreenWriter::Text(writer) => (writer.columns, writer.rows),
+        }
+    }
+}
+
+impl fmt::Write for ScreenWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        match self {
+            ScreenWriter::Framebuffer(console) => console
+                .as_mut()
+                .map_or(Ok(()), |console| console.write_str(s)),
+            ScreenWriter::Text(writer) => writer.write_str(s),
+        }
+    }
+}
+
+/// Locks `mutex`, forcing it open if it is held
+pub(crate) fn steal<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    if let Some(guard) = mutex.try_lock() {
+        return guard;
+    }
+    // The holder was interrupted by the panic and will never run again
+    unsafe { mutex.force_unlock() };
+    mutex.lock()
+}
+
+/// Cell the next `raw_print` character goes to, so successive calls don't overwrite each other
+static RAW_POSITION: AtomicUsize = AtomicUsize::new(0);
+
+pub struct RawWriter {
+    columns: usize,
+    rows: usize,
+    color: ColorCode,
+}
+
+impl RawWriter {
+    /// A writer in `color` for the current text mode size
+    fn new(color: ColorCode) -> RawWriter {
+        let (columns, rows) = match TEXT_MODE.try_lock() {
+            Some(mode) => (mode.columns(), mode.rows()),
+            None => (80, 25),
+        };
+        RawWriter {
+            columns,
+            rows,
+            color,
+        }
+    }
+}
+
+impl fmt::Write for RawWriter {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        let vga = 0xb8000 as *mut u16;
+        let color = self.color;
+        let screen = self.columns * self.rows;
+        let mut position = RAW_POSITION.load(Ordering::Relaxed);
+        for byte in s.bytes() {
+            match byte {
+                b'\n' => position = (position / self.columns + 1) * self.columns,
+                // Only ASCII is safe to print as is, anything else becomes a block
+                0x20..=0x7e => {
+                    let cell = (color.0 as u16) << 8 | byte as u16;
+                    unsafe { ptr::write_volatile(vga.add(position % screen), cell) };
+                    position += 1;
+                }
+                _ if byte & 0xc0 == 0x80 => {}
+                _ => {
+                    let cell = (color.0 as u16) << 8 | 0xfe;
+                    unsafe { ptr::write_volatile(vga.add(position % screen), cell) };
+                    position += 1;
+                }
+            }
+        }
+        RAW_POSITION.store(position % screen, Ordering::Relaxed);
+        Ok(())
+    }
+}
@@ -0,0 +1,106 @@
+//! Text mode fonts, uploaded to the character generator in plane 2 of video memory.
+//!
+//! The VGA draws each cell with the glyph its `ScreenChar` byte selects, so replacing glyphs here
+//! changes every cell showing them at once. The glyph slots are 32 bytes each, one byte per
+//! scanline; fonts shorter than the character cell get blank scanlines below each glyph.
+
+use core::ptr;
+use spin::Mutex;
+
+use crate::psf::Font;
+use crate::vga_graphics;
+use crate::vga_registers::PlaneAccess;
+
+/// Number of glyphs in the character generator
+pub const GLYPH_COUNT: usize = 256;
+/// Bytes reserved per glyph in plane 2, which is also the tallest supported glyph
+pub const GLYPH_SLOT_SIZE: usize = 32;
+
+/// Why a font couldn't be loaded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FontError {
+    /// The data is not a PSF1 or PSF2 file
+    InvalidFont,
+    /// Text mode glyphs are 8 pixels wide and at most 32 scanlines tall
+    UnsupportedSize,
+    /// A graphics mode is using plane 2, the font would be overwritten when going back to text mode
+    NotInTextMode,
+}
+
+/// The font found in plane 2 before the first upload, so `restore_default` can bring it back
+static DEFAULT_FONT: Mutex<Option<[u8; GLYPH_COUNT * GLYPH_SLOT_SIZE]>> = Mutex::new(None);
+
+/// Parses a PSF1 or PSF2 file and loads it as the text mode font
+#[allow(dead_code)]
+pub fn load_psf(data: &[u8]) -> Result<(), FontError> {
+    let font = Font::parse(data).ok_or(FontError::InvalidFont)?;
+    load(&font)
+}
+
+/// Replaces the glyphs of the character generator with the ones of `font`
+///
+/// The glyph indices are the ones of the font: glyph `n` shows for cells holding byte `n`, so a
+/// code page 437 font keeps `print!` output readable. Glyphs past the 256th are ignored, and
+/// slots past the end of a smaller font keep their glyph.
+#[allow(dead_code)]
+pub fn load(font: &Font) -> Result<(), FontError> {
+    if font.width() != 8 || font.height() > GLYPH_SLOT_SIZE {
+        return Err(FontError::UnsupportedSize);
+    }
+    with_font_memory(|memory, _| {
+        for index in 0..font.glyph_count().min(GLYPH_COUNT) {
+            if let Some(glyph) = font.glyph(index) {
+                write_slot(memory, index, glyph);
+            }
+        }
+    })
+}
+
+/// Replaces a single glyph, e.g. to draw a logo with a few custom characters
+///
+/// `rows` holds one byte per scanline, the leftmost pixel is the most significant bit.
+#[allow(dead_code)]
+pub fn set_glyph(index: u8, rows: &[u8]) -> Result<(), FontError> {
+    if rows.len() > GLYPH_SLOT_SIZE {
+        return Err(FontError::UnsupportedSize);
+    }
+    with_font_memory(|memory, _| write_slot(memory, index as usize, rows))
+}
+
+/// Loads the font that was there before the first upload again
+#[allow(dead_code)]
+pub fn restore_default() -> Result<(), FontError> {
+    with_font_memory(|memory, default| {
+        for (offset, &byte) in default.iter().enumerate() {
+            unsafe { ptr::write_volatile(memory.add(offset), byte) };
+        }
+    })
+}
+
+/// Runs `f` with plane 2 mapped at `memory` and the original font, which is saved on the first call
+fn with_font_memory<F: FnOnce(*mut u8, &[u8])>(f: F) -> Result<(), FontError> {
+    if vga_graphics::current_mode().is_some() {
+        return Err(FontError::NotInTextMode);
+    }
+    let mut plane = PlaneAccess::new(2);
+    let memory = plane.memory();
+    let mut saved = DEFAULT_FONT.lock();
+    let default = saved.get_or_insert_with(|| {
+        let mut font = [0; GLYPH_COUNT * GLYPH_SLOT_SIZE];
+        for (offset, byte) in font.iter_mut().enumerate() {
+            *byte = unsafe { ptr::read_volatile(memory.add(offset)) };
+        }
+        font
+    });
+    f(memory, &default[..]);
+    Ok(())
+}
+
+/// Writes `rows` to glyph slot `index`, blanking the rest of the slot
+fn write_slot(memory: *mut u8, index: usize, rows: &[u8]) {
+    let slot = index * GLYPH_SLOT_SIZE;
+    for line in 0..GLYPH_SLOT_SIZE {
+        let byte = rows.get(line).copied().unwrap_or(0);
+        unsafe { ptr::write_volatile(memory.add(slot + line), byte) };
+    }
+}
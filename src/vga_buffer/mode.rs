@@ -0,0 +1,115 @@
+//! Text mode resolutions and the register values selecting them.
+//!
+//! All modes use 8 pixel wide characters. The 90 column modes run the 640x480 timings of mode
+//! 12h (28 MHz dot clock), the 50 and 60 row modes halve the character cell to 8 scanlines and
+//! need an 8x8 font, which `vga_buffer::set_text_mode` loads.
+
+use crate::vga_registers::ModeRegisters;
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextMode {
+    /// The mode the BIOS leaves us in, 8x16 font
+    Text80x25,
+    /// 8x8 font on the 400 lines of 80x25
+    Text80x50,
+    /// 8x16 font on 480 lines
+    Text90x30,
+    /// 8x8 font on 480 lines
+    Text90x60,
+}
+
+/// Largest number of columns of all text modes, sizing the buffers of the consoles
+pub const MAX_COLUMNS: usize = 90;
+/// Largest number of rows of all text modes
+pub const MAX_ROWS: usize = 60;
+
+impl TextMode {
+    pub fn columns(self) -> usize {
+        match self {
+            TextMode::Text80x25 | TextMode::Text80x50 => 80,
+            TextMode::Text90x30 | TextMode::Text90x60 => 90,
+        }
+    }
+
+    pub fn rows(self) -> usize {
+        match self {
+            TextMode::Text80x25 => 25,
+            TextMode::Text80x50 => 50,
+            TextMode::Text90x30 => 30,
+            TextMode::Text90x60 => 60,
+        }
+    }
+
+    /// Scanlines per character cell, which is the height of the font
+    pub fn char_height(self) -> usize {
+        match self {
+            TextMode::Text80x25 | TextMode::Text90x30 => 16,
+            TextMode::Text80x50 | TextMode::Text90x60 => 8,
+        }
+    }
+
+    pub fn registers(self) -> &'static ModeRegisters {
+        match self {
+            TextMode::Text80x25 => &MODE_80X25,
+            TextMode::Text80x50 => &MODE_80X50,
+            TextMode::Text90x30 => &MODE_90X30,
+            TextMode::Text90x60 => &MODE_90X60,
+        }
+    }
+}
+
+/// Sequencer, graphics controller and attribute controller values shared by every text mode
+const TEXT_SEQUENCER_8_DOTS: [u8; 5] = [0x03, 0x01, 0x03, 0x00, 0x02];
+const TEXT_GRAPHICS: [u8; 9] = [0x00, 0x00, 0x00, 0x00, 0x00, 0x10, 0x0e, 0x00, 0xff];
+const TEXT_ATTRIBUTE: [u8; 21] = [
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x14, 0x07, 0x38, 0x39, 0x3a, 0x3b, 0x3c, 0x3d, 0x3e, 0x3f,
+    0x0c, 0x00, 0x0f, 0x08, 0x00,
+];
+
+/// The BIOS mode 3, with its 9 pixel wide characters
+const MODE_80X25: ModeRegisters = ModeRegisters {
+    misc: 0x67,
+    sequencer: [0x03, 0x00, 0x03, 0x00, 0x02],
+    crtc: [
+        0x5f, 0x4f, 0x50, 0x82, 0x55, 0x81, 0xbf, 0x1f, 0x00, 0x4f, 0x0d, 0x0e, 0x00, 0x00, 0x00,
+        0x50, 0x9c, 0x0e, 0x8f, 0x28, 0x1f, 0x96, 0xb9, 0xa3, 0xff,
+    ],
+    graphics: TEXT_GRAPHICS,
+    attribute: TEXT_ATTRIBUTE,
+};
+
+/// 80x25 with a maximum scan line of 7 and the cursor on scanlines 6-7
+const MODE_80X50: ModeRegisters = ModeRegisters {
+    misc: 0x67,
+    sequencer: [0x03, 0x00, 0x03, 0x00, 0x02],
+    crtc: [
+        0x5f, 0x4f, 0x50, 0x82, 0x55, 0x81, 0xbf, 0x1f, 0x00, 0x47, 0x06, 0x07, 0x00, 0x00, 0x00,
+        0x00, 0x9c, 0x0e, 0x8f, 0x28, 0x1f, 0x96, 0xb9, 0xa3, 0xff,
+    ],
+    graphics: TEXT_GRAPHICS,
+    attribute: TEXT_ATTRIBUTE,
+};
+
+/// 720 pixels wide, the row offset (0x13) is 45 words
+const MODE_90X30: ModeRegisters = ModeRegisters {
+    misc: 0xe7,
+    sequencer: TEXT_SEQUENCER_8_DOTS,
+    crtc: [
+        0x6b, 0x59, 0x5a, 0x82, 0x60, 0x8d, 0x0b, 0x3e, 0x00, 0x4f, 0x0d, 0x0e, 0x00, 0x00, 0x00,
+        0x00, 0xea, 0x0c, 0xdf, 0x2d, 0x10, 0xe8, 0x05, 0xa3, 0xff,
+    ],
+    graphics: TEXT_GRAPHICS,
+    attribute: TEXT_ATTRIBUTE,
+};
+
+const MODE_90X60: ModeRegisters = ModeRegisters {
+    misc: 0xe7,
+    sequencer: TEXT_SEQUENCER_8_DOTS,
+    crtc: [
+        0x6b, 0x59, 0x5a, 0x82, 0x60, 0x8d, 0x0b, 0x3e, 0x00, 0x47, 0x06, 0x07, 0x00, 0x00, 0x00,
+        0x00, 0xea, 0x0c, 0xdf, 0x2d, 0x08, 0xe8, 0x05, 0xa3, 0xff,
+    ],
+    graphics: TEXT_GRAPHICS,
+    attribute: TEXT_ATTRIBUTE,
+};
@@ -0,0 +1,116 @@
+//! Rectangular areas of the text buffer with their own cursor, scrolling, wrapping and color.
+//!
+//! A region doesn't own any screen memory: it is drawn through `Writer::write_region`, which
+//! lends it the buffer of the console. Only `\n` and `\r` are interpreted, escape sequences are not.
+
+use super::shadow::ShadowBuffer;
+use super::mode::{MAX_COLUMNS, MAX_ROWS};
+use super::{cp437, ColorCode, ScreenChar};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    top: usize,
+    left: usize,
+    height: usize,
+    width: usize,
+    /// Cursor position relative to the top left corner of the region
+    row: usize,
+    column: usize,
+    color_code: ColorCode,
+    /// Whether text running past the right edge continues on the next row or is cut off
+    wrap: bool,
+}
+
+#[allow(dead_code)]
+impl Region {
+    /// Creates a region of `height` rows and `width` columns starting at `top`/`left`
+    ///
+    /// The rectangle is clipped to the largest text mode and is at least one cell large. The parts
+    /// lying outside of the current text mode are drawn but not shown.
+    pub fn new(top: usize, left: usize, height: usize, width: usize, color_code: ColorCode) -> Region {
+        let top = top.min(MAX_ROWS - 1);
+        let left = left.min(MAX_COLUMNS - 1);
+        Region {
+            top,
+            left,
+            height: height.clamp(1, MAX_ROWS - top),
+            width: width.clamp(1, MAX_COLUMNS - left),
+            row: 0,
+            column: 0,
+            color_code,
+            wrap: true,
+        }
+    }
+
+    /// Returns `(top, left, height, width)`
+    pub fn bounds(&self) -> (usize, usize, usize, usize) {
+        (self.top, self.left, self.height, self.width)
+    }
+
+    pub fn set_color(&mut self, color_code: ColorCode) {
+        self.color_code = color_code;
+    }
+
+    pub fn set_wrap(&mut self, wrap: bool) {
+        self.wrap = wrap;
+    }
+
+    /// Moves the region's cursor, relative to its top left corner and clamped to it
+    pub fn set_position(&mut self, row: usize, column: usize) {
+        self.row = row.min(self.height - 1);
+        self.column = column.min(self.width - 1);
+    }
+
+    /// Fills the region with blanks in its color and moves the cursor to its top left corner
+    pub(super) fn clear(&mut self, buffer: &mut ShadowBuffer) {
+        for row in 0..self.height {
+            self.clear_row(buffer, row);
+        }
+        self.row = 0;
+        self.column = 0;
+    }
+
+    pub(super) fn write_str(&mut self, buffer: &mut ShadowBuffer, s: &str) {
+        for c in s.chars() {
+            match c {
+                '\n' => self.new_line(buffer),
+                '\r' => self.column = 0,
+                c => self.put_glyph(buffer, cp437::from_char(c).unwrap_or(0xfe)),
+            }
+        }
+    }
+
+    fn put_glyph(&mut self, buffer: &mut ShadowBuffer, byte: u8) {
+        if self.column >= self.width {
+            if !self.wrap {
+                return;
+            }
+            self.new_line(buffer);
+        }
+        buffer.write(self.top + self.row, self.left + self.column, ScreenChar {
+            ascii_char: byte,
+            color_code: self.color_code,
+        });
+        self.column += 1;
+    }
+
+    /// Moves to the start of the next row, scrolling the region's content up when on its last row
+    fn new_line(&mut self, buffer: &mut ShadowBuffer) {
+        self.column = 0;
+        if self.row < self.height - 1 {
+            self.row += 1;
+            return;
+        }
+        for row in self.top + 1..self.top + self.height {
+            for col in self.left..self.left + self.width {
+                let character = buffer.read(row, col);
+                buffer.write(row - 1, col, character);
+            }
+        }
+        self.clear_row(buffer, self.height - 1);
+    }
+
+    fn clear_row(&self, buffer: &mut ShadowBuffer, row: usize) {
+        buffer.fill(self.top + row, self.left, self.left + self.width, ScreenChar::blank(self.color_code));
+    }
+}
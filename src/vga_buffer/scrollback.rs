@@ -0,0 +1,53 @@
+//! Ring buffer of the lines that scrolled off the top of the screen.
+
+use super::mode::MAX_COLUMNS;
+use super::ScreenChar;
+
+/// Number of past lines kept, the oldest ones are overwritten once it is full
+pub const SCROLLBACK_LINES: usize = 100;
+
+/// One full row of the text buffer, as wide as the widest text mode
+pub type Line = [ScreenChar; MAX_COLUMNS];
+
+pub struct Scrollback {
+    lines: &'static mut [Line; SCROLLBACK_LINES],
+    /// Index of the oldest line in `lines`
+    start: usize,
+    len: usize,
+}
+
+impl Scrollback {
+    /// Creates an empty scrollback storing its lines in `lines`
+    pub fn new(lines: &'static mut [Line; SCROLLBACK_LINES]) -> Scrollback {
+        Scrollback {
+            lines,
+            start: 0,
+            len: 0,
+        }
+    }
+
+    /// Number of lines currently stored
+    pub fn line_count(&self) -> usize {
+        self.len
+    }
+
+    /// Appends a line, dropping the oldest one if the ring is full
+    pub fn push(&mut self, line: Line) {
+        let end = (self.start + self.len) % SCROLLBACK_LINES;
+        self.lines[end] = line;
+        if self.len < SCROLLBACK_LINES {
+            self.len += 1;
+        } else {
+            self.start = (self.start + 1) % SCROLLBACK_LINES;
+        }
+    }
+
+    /// Returns the line at `index`, 0 being the oldest one stored
+    pub fn get(&self, index: usize) -> Option<&Line> {
+        if index < self.len {
+            Some(&self.lines[(self.start + index) % SCROLLBACK_LINES])
+        } else {
+            None
+        }
+    }
+}
@@ -0,0 +1,90 @@
+//! In-memory copy of a screen, flushed to VGA memory one changed row at a time.
+//!
+//! Reading VGA memory is slow and every write to it is a volatile access, so the writers draw
+//! into a shadow buffer in normal RAM instead. Scrolling becomes a single `memmove` and a flush
+//! only touches the rows that changed since the last one.
+
+use super::mode::{MAX_COLUMNS, MAX_ROWS};
+use super::{Buffer, ScreenChar, EMPTY_CELL};
+use super::scrollback::Line;
+
+/// Room for the largest text mode, of which the `columns` x `rows` top left part is on screen
+pub struct ShadowBuffer {
+    chars: [Line; MAX_ROWS],
+    /// Rows changed since the last flush
+    dirty: [bool; MAX_ROWS],
+    columns: usize,
+    rows: usize,
+}
+
+impl ShadowBuffer {
+    /// A blank screen of the largest size, with nothing to flush
+    pub const fn new() -> ShadowBuffer {
+        ShadowBuffer {
+            chars: [[EMPTY_CELL; MAX_COLUMNS]; MAX_ROWS],
+            dirty: [false; MAX_ROWS],
+            columns: MAX_COLUMNS,
+            rows: MAX_ROWS,
+        }
+    }
+
+    /// Changes the part of the buffer that is on screen, the next flush copies all of it
+    pub fn set_size(&mut self, columns: usize, rows: usize) {
+        self.columns = columns.min(MAX_COLUMNS);
+        self.rows = rows.min(MAX_ROWS);
+        self.mark_all_dirty();
+    }
+
+    pub fn read(&self, row: usize, col: usize) -> ScreenChar {
+        self.chars[row][col]
+    }
+
+    pub fn write(&mut self, row: usize, col: usize, character: ScreenChar) {
+        self.chars[row][col] = character;
+        self.dirty[row] = true;
+    }
+
+    pub fn row(&self, row: usize) -> &Line {
+        &self.chars[row]
+    }
+
+    pub fn set_row(&mut self, row: usize, line: &Line) {
+        self.chars[row] = *line;
+        self.dirty[row] = true;
+    }
+
+    /// Sets the columns `from..to` of `row` to `character`
+    pub fn fill(&mut self, row: usize, from: usize, to: usize, character: ScreenChar) {
+        for cell in &mut self.chars[row][from..to] {
+            *cell = character;
+        }
+        self.dirty[row] = true;
+    }
+
+    /// Moves the rows `top + 1..=bottom` up by one, the content of `bottom` is left as is
+    pub fn scroll_up(&mut self, top: usize, bottom: usize) {
+        self.chars.copy_within(top + 1..=bottom, top);
+        for dirty in &mut self.dirty[top..=bottom] {
+            *dirty = true;
+        }
+    }
+
+    /// Forces the next flush to copy the whole screen
+    pub fn mark_all_dirty(&mut self) {
+        self.dirty = [true; MAX_ROWS];
+    }
+
+    /// Copies the rows changed since the last flush to `vga`
+    pub fn flush(&mut self, vga: &mut Buffer) {
+        for row in 0..self.rows {
+            if !self.dirty[row] {
+                continue;
+            }
+            let start = row * self.columns;
+            for (col, &character) in self.chars[row][..self.columns].iter().enumerate() {
+                vga.chars[start + col].write(character);
+            }
+            self.dirty[row] = false;
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Color themes: what the 16 `Color`s look like, and the default colors of the consoles.
+//!
+//! The `Color` of a cell doesn't name an RGB value, it goes through the palette registers of the
+//! attribute controller to one of the 256 DAC entries, which hold the actual color. A theme
+//! reprograms these DAC entries, so it recolors everything on screen at once.
+
+use spin::Mutex;
+
+use crate::vga_graphics;
+use crate::vga_registers;
+
+use super::{Color, ColorCode, CONSOLES};
+
+/// DAC entry behind each `Color` in text mode, as set up by the attribute palette registers
+const TEXT_DAC_INDICES: [u8; 16] = [
+    0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x14, 0x07, 0x38, 0x39, 0x3a, 0x3b, 0x3c, 0x3d, 0x3e, 0x3f,
+];
+
+/// A palette for the 16 `Color`s and the colors consoles use by default
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    pub name: &'static str,
+    /// 24-bit `[red, green, blue]` values in `Color` order, the DAC keeps the top 6 bits of each
+    pub colors: [[u8; 3]; 16],
+    pub foreground: Color,
+    pub background: Color,
+}
+
+impl Theme {
+    /// The default colors of the theme as a `ColorCode`
+    pub fn color_code(&self) -> ColorCode {
+        ColorCode::new(self.foreground, self.background)
+    }
+}
+
+/// The colors the VGA BIOS sets up
+pub const VGA: Theme = Theme {
+    name: "vga",
+    colors: [
+        [0x00, 0x00, 0x00],
+        [0x00, 0x00, 0xaa],
+        [0x00, 0xaa, 0x00],
+        [0x00, 0xaa, 0xaa],
+        [0xaa, 0x00, 0x00],
+        [0xaa, 0x00, 0xaa],
+        [0xaa, 0x55, 0x00],
+        [0xaa, 0xaa, 0xaa],
+        [0x55, 0x55, 0x55],
+        [0x55, 0x55, 0xff],
+        [0x55, 0xff, 0x55],
+        [0x55, 0xff, 0xff],
+        [0xff, 0x55, 0x55],
+        [0xff, 0x55, 0xff],
+        [0xff, 0xff, 0x55],
+        [0xff, 0xff, 0xff],
+    ],
+    foreground: Color::Yellow,
+    background: Color::Black,
+};
+
+/// Solarized dark: its accent colors where it has them, its base tones for the greys
+///
+/// Violet and orange are the bright magenta and red, blue and yellow are brightened halfway to
+/// base3 since Solarized has no lighter variant of them.
+#[allow(dead_code)]
+pub const SOLARIZED_DARK: Theme = Theme {
+    name: "solarized-dark",
+    colors: [
+        [0x00, 0x2b, 0x36],
+        [0x26, 0x8b, 0xd2],
+        [0x85, 0x99, 0x00],
+        [0x2a, 0xa1, 0x98],
+        [0xdc, 0x32, 0x2f],
+        [0xd3, 0x36, 0x82],
+        [0xb5, 0x89, 0x00],
+        [0x83, 0x94, 0x96],
+        [0x58, 0x6e, 0x75],
+        [0x91, 0xc0, 0xda],
+        [0x93, 0xa1, 0xa1],
+        [0xee, 0xe8, 0xd5],
+        [0xcb, 0x4b, 0x16],
+        [0x6c, 0x71, 0xc4],
+        [0xd9, 0xbf, 0x71],
+        [0xfd, 0xf6, 0xe3],
+    ],
+    foreground: Color::LightGray,
+    background: Color::Black,
+};
+
+/// Fully saturated colors on black, bright variants mixed with white
+#[allow(dead_code)]
+pub const HIGH_CONTRAST: Theme = Theme {
+    name: "high-contrast",
+    colors: [
+        [0x00, 0x00, 0x00],
+        [0x00, 0x00, 0xff],
+        [0x00, 0xff, 0x00],
+        [0x00, 0xff, 0xff],
+        [0xff, 0x00, 0x00],
+        [0xff, 0x00, 0xff],
+        [0xff, 0xaa, 0x00],
+        [0xff, 0xff, 0xff],
+        [0x80, 0x80, 0x80],
+        [0x80, 0x80, 0xff],
+        [0x80, 0xff, 0x80],
+        [0x80, 0xff, 0xff],
+        [0xff, 0x80, 0x80],
+        [0xff, 0x80, 0xff],
+        [0xff, 0xff, 0x00],
+        [0xff, 0xff, 0xff],
+    ],
+    foreground: Color::White,
+    background: Color::Black,
+};
+
+/// The applied theme, with the changes made by `set_color` since
+static CURRENT: Mutex<Theme> = Mutex::new(VGA);
+
+/// Returns the applied theme, including the colors changed by `set_color`
+pub fn current() -> Theme {
+    *CURRENT.lock()
+}
+
+/// Programs the colors of `theme` and makes its default colors the ones of every console
+///
+/// Consoles currently writing in their old default colors switch to the new ones, text already
+/// on screen keeps its `Color`s, which the new palette repaints.
+#[allow(dead_code)]
+pub fn apply(theme: &Theme) {
+    *CURRENT.lock() = *theme;
+    for (index, &rgb) in theme.colors.iter().enumerate() {
+        write_color(index, rgb);
+    }
+    for console in CONSOLES.iter() {
+        console.lock().set_default_color_code(theme.color_code());
+    }
+}
+
+/// Changes what `color` looks like, in 24-bit `[red, green, blue]`
+#[allow(dead_code)]
+pub fn set_color(color: Color, rgb: [u8; 3]) {
+    CURRENT.lock().colors[color as usize] = rgb;
+    write_color(color as usize, rgb);
+}
+
+fn write_color(index: usize, rgb: [u8; 3]) {
+    // The graphics modes map the 16 colors straight to the first DAC entries
+    let dac_index = match vga_graphics::current_mode() {
+        Some(_) => index as u8,
+        None => TEXT_DAC_INDICES[index],
+    };
+    vga_registers::write_dac(dac_index, &[to_dac(rgb)]);
+}
+
+/// Scales a 24-bit color down to the 6 bits per component of the DAC
+pub fn to_dac(rgb: [u8; 3]) -> [u8; 3] {
+    [rgb[0] >> 2, rgb[1] >> 2, rgb[2] >> 2]
+}
@@ -0,0 +1,457 @@
+//! VGA graphics modes with a small pixel drawing API.
+//!
+//! `enter` switches from text mode into 320x200 with 256 colors (mode 13h) or 640x480 with 16
+//! colors (mode 12h) and returns a `Canvas` to draw on, `leave` goes back to text mode with the
+//! console content intact. In both graphics modes the colors 0 to 15 are the ones of
+//! `vga_buffer::Color` in the current theme, so `Color::Red as u8` draws red.
+//!
+//! To check the output under QEMU, run with `-monitor stdio` and use `screendump shot.ppm`.
+
+use core::cmp;
+use core::ptr;
+use spin::Mutex;
+
+use crate::vga_buffer::{self, theme};
+use crate::vga_registers::{self, ModeRegisters, PlaneAccess};
+
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Mode 13h: 320x200, 256 colors, one byte per pixel
+    Graphics320x200x256,
+    /// Mode 12h: 640x480, 16 colors, four bit planes
+    Graphics640x480x16,
+}
+
+impl Mode {
+    pub fn width(self) -> usize {
+        match self {
+            Mode::Graphics320x200x256 => 320,
+            Mode::Graphics640x480x16 => 640,
+        }
+    }
+
+    pub fn height(self) -> usize {
+        match self {
+            Mode::Graphics320x200x256 => 200,
+            Mode::Graphics640x480x16 => 480,
+        }
+    }
+
+    fn registers(self) -> &'static ModeRegisters {
+        match self {
+            Mode::Graphics320x200x256 => &MODE_320X200X256,
+            Mode::Graphics640x480x16 => &MODE_640X480X16,
+        }
+    }
+}
+
+const MODE_320X200X256: ModeRegisters = ModeRegisters {
+    misc: 0x63,
+    sequencer: [0x03, 0x01, 0x0f, 0x00, 0x0e],
+    crtc: [
+        0x5f, 0x4f, 0x50, 0x82, 0x54, 0x80, 0xbf, 0x1f, 0x00, 0x41, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0x9c, 0x0e, 0x8f, 0x28, 0x40, 0x96, 0xb9, 0xa3, 0xff,
+    ],
+    graphics: [0x00, 0x00, 0x00, 0x00, 0x00, 0x40, 0x05, 0x0f, 0xff],
+    attribute: [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f, 0x41, 0x00, 0x0f, 0x00, 0x00,
+    ],
+};
+
+/// The palette registers are the identity, so pixel values index the DAC directly like in mode 13h
+///
+/// All four planes are enabled in the map mask, write mode 2 then sets every bit of a pixel at once.
+const MODE_640X480X16: ModeRegisters = ModeRegisters {
+    misc: 0xe3,
+    sequencer: [0x03, 0x01, 0x0f, 0x00, 0x06],
+    crtc: [
+        0x5f, 0x4f, 0x50, 0x82, 0x54, 0x80, 0x0b, 0x3e, 0x00, 0x40, 0x00, 0x00, 0x00, 0x00, 0x00,
+        0x00, 0xea, 0x0c, 0xdf, 0x28, 0x00, 0xe7, 0x04, 0xe3, 0xff,
+    ],
+    graphics: [0x00, 0x00, 0x00, 0x00, 0x03, 0x00, 0x05, 0x0f, 0xff],
+    attribute: [
+        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, 0x0c, 0x0d, 0x0e,
+        0x0f, 0x01, 0x00, 0x0f, 0x00, 0x00,
+    ],
+};
+
+const VIDEO_MEMORY: usize = 0xa0000;
+/// Bytes per row of one plane in mode 12h, each byte holds 8 pixels
+const PLANAR_BYTES_PER_ROW: usize = 640 / 8;
+/// Size of the font in plane 2: 256 glyphs with 32 bytes reserved per glyph
+const FONT_SIZE: usize = 256 * 32;
+
+/// What text mode needs back after a graphics mode overwrote it
+struct SavedText {
+    font: [u8; FONT_SIZE],
+    palette: [[u8; 3]; 256],
+}
+
+struct State {
+    mode: Option<Mode>,
+    saved: SavedText,
+}
+
+static STATE: Mutex<State> = Mutex::new(State {
+    mode: None,
+    saved: SavedText {
+        font: [0; FONT_SIZE],
+        palette: [[0; 3]; 256],
+    },
+});
+
+/// Returns the current graphics mode, `None` in text mode
+#[allow(dead_code)]
+pub fn current_mode() -> Option<Mode> {
+    STATE.lock().mode
+}
+
+/// Switches to `mode` and returns a canvas covering the whole screen, cleared to black
+///
+/// Coming from text mode, its font and palette are saved first so `leave` can bring them back.
+#[allow(dead_code)]
+pub fn enter(mode: Mode) -> Canvas {
+    let mut state = STATE.lock();
+    if state.mode.is_none() {
+        save_text(&mut state.saved);
+    }
+    vga_registers::set_mode_registers(mode.registers());
+    state.mode = Some(mode);
+    drop(state);
+
+    load_default_palette();
+    let mut canvas = Canvas { mode };
+    if mode == Mode::Graphics640x480x16 {
+        // Write mode 2: the CPU data is a color, the bit mask register selects the pixels
+        vga_registers::write_graphics(0x05, 0x02);
+    }
+    canvas.clear(0);
+    canvas
+}
+
+/// Goes back to the text mode in use before `enter` and redraws the active console
+#[allow(dead_code)]
+pub fn leave() {
+    let mut state = STATE.lock();
+    if state.mode.is_none() {
+        return;
+    }
+    vga_registers::set_mode_registers(vga_buffer::text_mode().registers());
+    vga_buffer::apply_blink_mode();
+    restore_text(&state.saved);
+    state.mode = None;
+    drop(state);
+    vga_buffer::redraw_active_console();
+}
+
+fn save_text(saved: &mut SavedText) {
+    let mut plane = PlaneAccess::new(2);
+    let font = plane.memory();
+    for (offset, byte) in saved.font.iter_mut().enumerate() {
+        *byte = unsafe { ptr::read_volatile(font.add(offset)) };
+    }
+    drop(plane);
+    vga_registers::read_dac(0, &mut saved.palette);
+}
+
+fn restore_text(saved: &SavedText) {
+    let mut plane = PlaneAccess::new(2);
+    let font = plane.memory();
+    for (offset, &byte) in saved.font.iter().enumerate() {
+        unsafe { ptr::write_volatile(font.add(offset), byte) };
+    }
+    drop(plane);
+    vga_registers::write_dac(0, &saved.palette);
+}
+
+/// The colors of the console theme, then a grey ramp and a 6x6x6 color cube for the 256 color mode
+fn load_default_palette() {
+    for (index, &rgb) in theme::current().colors.iter().enumerate() {
+        vga_registers::write_dac(index as u8, &[theme::to_dac(rgb)]);
+    }
+    // Scales `level` out of `steps - 1` to the 0-63 range of the DAC
+    let scale = |level: u16, steps: u16| (level * 0x3f / (steps - 1)) as u8;
+    for level in 0..16 {
+        let grey = scale(level, 16);
+        vga_registers::write_dac(16 + level as u8, &[[grey, grey, grey]]);
+    }
+    let mut index = 32u8;
+    for red in 0..6 {
+        for green in 0..6 {
+            for blue in 0..6 {
+                vga_registers::write_dac(index, &[[scale(red, 6), scale(green, 6), scale(blue, 6)]]);
+                index += 1;
+            }
+        }
+    }
+}
+
+/// A rectangular block of pixels, one byte per pixel, row after row
+pub struct Bitmap<'a> {
+    width: usize,
+    height: usize,
+    pixels: &'a [u8],
+    transparent: Option<u8>,
+}
+
+#[allow(dead_code)]
+impl<'a> Bitmap<'a> {
+    /// Returns `None` if `pixels` doesn't hold exactly `width * height` bytes
+    pub fn new(width: usize, height: usize, pixels: &'a [u8]) -> Option<Bitmap<'a>> {
+        if pixels.len() != width * height {
+            return None;
+        }
+        Some(Bitmap {
+            width,
+            height,
+            pixels,
+            transparent: None,
+        })
+    }
+
+    /// Pixels of `color` are skipped when blitting
+    pub fn with_transparent(mut self, color: u8) -> Bitmap<'a> {
+        self.transparent = Some(color);
+        self
+    }
+}
+
+/// Handle to draw on the screen in a graphics mode
+///
+/// Coordinates are signed so shapes may lie partly off-screen, everything is clipped to the screen.
+#[derive(Debug)]
+pub struct Canvas {
+    mode: Mode,
+}
+
+#[allow(dead_code)]
+impl Canvas {
+    pub fn width(&self) -> usize {
+        self.mode.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.mode.height()
+    }
+
+    pub fn set_pixel(&mut self, x: i32, y: i32, color: u8) {
+        if x < 0 || y < 0 || x as usize >= self.width() || y as usize >= self.height() {
+            return;
+        }
+        let (x, y) = (x as usize, y as usize);
+        match self.mode {
+            Mode::Graphics320x200x256 => unsafe {
+                ptr::write_volatile((VIDEO_MEMORY + y * 320 + x) as *mut u8, color);
+            },
+            Mode::Graphics640x480x16 => self.write_planar(y * PLANAR_BYTES_PER_ROW + x / 8, 0x80 >> (x % 8), color),
+        }
+    }
+
+    /// Writes `color` to the pixels of the planar byte at `offset` selected by `mask`
+    fn write_planar(&mut self, offset: usize, mask: u8, color: u8) {
+        vga_registers::write_graphics(0x08, mask);
+        let address = (VIDEO_MEMORY + offset) as *mut u8;
+        unsafe {
+            // The read loads the latches, so the pixels outside of the mask keep their color
+            ptr::read_volatile(address);
+            ptr::write_volatile(address, color);
+        }
+    }
+
+    /// Draws the pixels `x0..=x1` of row `y`
+    fn horizontal_line(&mut self, x0: i32, x1: i32, y: i32, color: u8) {
+        if y < 0 || y as usize >= self.height() {
+            return;
+        }
+        let (left, right) = (cmp::min(x0, x1), cmp::max(x0, x1));
+        let x0 = cmp::max(left, 0);
+        let x1 = cmp::min(right, self.width() as i32 - 1);
+        if x0 > x1 {
+            return;
+        }
+        let (x0, x1, y) = (x0 as usize, x1 as usize, y as usize);
+        match self.mode {
+            Mode::Graphics320x200x256 => unsafe {
+                let row = (VIDEO_MEMORY + y * 320) as *mut u8;
+                for x in x0..=x1 {
+                    ptr::write_volatile(row.add(x), color);
+                }
+            },
+            Mode::Graphics640x480x16 => {
+                // Whole bytes at once, with masks for the partial bytes at both ends
+                let row = y * PLANAR_BYTES_PER_ROW;
+                let (first, last) = (x0 / 8, x1 / 8);
+                let first_mask = 0xffu8 >> (x0 % 8);
+                let last_mask = 0xffu8 << (7 - x1 % 8);
+                if first == last {
+                    self.write_planar(row + first, first_mask & last_mask, color);
+                    return;
+                }
+                self.write_planar(row + first, first_mask, color);
+                for byte in first + 1..last {
+                    self.write_planar(row + byte, 0xff, color);
+                }
+                self.write_planar(row + last, last_mask, color);
+            }
+        }
+    }
+
+    pub fn clear(&mut self, color: u8) {
+        self.fill_rect(0, 0, self.width() as i32, self.height() as i32, color);
+    }
+
+    /// Draws the 8 pixels from `(x, y)` to the right, in `foreground` where `bits` has a 1 (most
+    /// significant bit first) and in `background` elsewhere
+    ///
+    /// This is how text is rendered, in mode 12h it takes two writes when `x` is a multiple of 8.
+    pub fn draw_bits(&mut self, x: i32, y: i32, bits: u8, foreground: u8, background: u8) {
+        let on_screen = x >= 0 && y >= 0 && x as usize + 8 <= self.width() && (y as usize) < self.height();
+        if self.mode == Mode::Graphics640x480x16 && on_screen && x % 8 == 0 {
+            let offset = y as usize * PLANAR_BYTES_PER_ROW + x as usize / 8;
+            self.write_planar(offset, bits, foreground);
+            self.write_planar(offset, !bits, background);
+            return;
+        }
+        for bit in 0..8 {
+            let color = if bits & (0x80 >> bit) != 0 { foreground } else { background };
+            self.set_pixel(x + bit, y, color);
+        }
+    }
+
+    /// Moves the whole picture up by `rows` pixels and fills the rows freed at the bottom with `color`
+    pub fn scroll_up(&mut self, rows: usize, color: u8) {
+        let rows = cmp::min(rows, self.height());
+        let kept = self.height() - rows;
+        match self.mode {
+            Mode::Graphics320x200x256 => {
+                let memory = VIDEO_MEMORY as *mut u8;
+                for offset in 0..kept * 320 {
+                    unsafe {
+                        let pixel = ptr::read_volatile(memory.add(offset + rows * 320));
+                        ptr::write_volatile(memory.add(offset), pixel);
+                    }
+                }
+            }
+            Mode::Graphics640x480x16 => {
+                // Write mode 1 stores the latches, so each byte read and written moves 8 pixels of
+                // all four planes at once
+                vga_registers::write_graphics(0x05, 0x01);
+                let memory = VIDEO_MEMORY as *mut u8;
+                for offset in 0..kept * PLANAR_BYTES_PER_ROW {
+                    unsafe {
+                        ptr::read_volatile(memory.add(offset + rows * PLANAR_BYTES_PER_ROW));
+                        ptr::write_volatile(memory.add(offset), 0);
+                    }
+                }
+                vga_registers::write_graphics(0x05, 0x02);
+            }
+        }
+        self.fill_rect(0, kept as i32, self.width() as i32, rows as i32, color);
+    }
+
+    /// Bresenham's line from `(x0, y0)` to `(x1, y1)`, both ends included
+    pub fn draw_line(&mut self, x0: i32, y0: i32, x1: i32, y1: i32, color: u8) {
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let step_x = if x0 < x1 { 1 } else { -1 };
+        let step_y = if y0 < y1 { 1 } else { -1 };
+        let (mut x, mut y) = (x0, y0);
+        let mut error = dx + dy;
+        loop {
+            self.set_pixel(x, y, color);
+            if x == x1 && y == y1 {
+                break;
+            }
+            let doubled = 2 * error;
+            if doubled >= dy {
+                error += dy;
+                x += step_x;
+            }
+            if doubled <= dx {
+                error += dx;
+                y += step_y;
+            }
+        }
+    }
+
+    /// Outline of the `width` x `height` rectangle with its top left corner at `(x, y)`
+    pub fn draw_rect(&mut self, x: i32, y: i32, width: i32, height: i32, color: u8) {
+        if width <= 0 || height <= 0 {
+            return;
+        }
+        let (right, bottom) = (x + width - 1, y + height - 1);
+        self.horizontal_line(x, right, y, color);
+        self.horizontal_line(x, right, bottom, color);
+        self.draw_line(x, y, x, bottom, color);
+        self.draw_line(right, y, right, bottom, color);
+    }
+
+    pub fn fill_rect(&mut self, x: i32, y: i32, width: i32, height: i32, color: u8) {
+        for row in y..y + height {
+            self.horizontal_line(x, x + width - 1, row, color);
+        }
+    }
+
+    /// Midpoint circle around `(center_x, center_y)`
+    pub fn draw_circle(&mut self, center_x: i32, center_y: i32, radius: i32, color: u8) {
+        self.circle(radius, |canvas, x, y| {
+            for &(px, py) in &[(x, y), (y, x), (-y, x), (-x, y), (-x, -y), (-y, -x), (y, -x), (x, -y)] {
+                canvas.set_pixel(center_x + px, center_y + py, color);
+            }
+        });
+    }
+
+    pub fn fill_circle(&mut self, center_x: i32, center_y: i32, radius: i32, color: u8) {
+        self.circle(radius, |canvas, x, y| {
+            canvas.horizontal_line(center_x - x, center_x + x, center_y + y, color);
+            canvas.horizontal_line(center_x - x, center_x + x, center_y - y, color);
+            canvas.horizontal_line(center_x - y, center_x + y, center_y + x, color);
+            canvas.horizontal_line(center_x - y, center_x + y, center_y - x, color);
+        });
+    }
+
+    /// Walks one octant of the circle, `plot` mirrors each `(x, y)` offset as it needs
+    fn circle<F: FnMut(&mut Canvas, i32, i32)>(&mut self, radius: i32, mut plot: F) {
+        if radius < 0 {
+            return;
+        }
+        let (mut x, mut y) = (radius, 0);
+        let mut error = 1 - radius;
+        while x >= y {
+            plot(self, x, y);
+            y += 1;
+            if error < 0 {
+                error += 2 * y + 1;
+            } else {
+                x -= 1;
+                error += 2 * (y - x) + 1;
+            }
+        }
+    }
+
+    /// Copies `bitmap` with its top left corner at `(x, y)`
+    pub fn blit(&mut self, x: i32, y: i32, bitmap: &Bitmap) {
+        for row in 0..bitmap.height {
+            for col in 0..bitmap.width {
+                let color = bitmap.pixels[row * bitmap.width + col];
+                if Some(color) != bitmap.transparent {
+                    self.set_pixel(x + col as i32, y + row as i32, color);
+                }
+            }
+        }
+    }
+
+    /// Sets the DAC color behind pixel value `index` (only 0 to 15 are visible in the 16 color mode)
+    ///
+    /// The components range from 0 to 63.
+    pub fn set_palette_entry(&mut self, index: u8, red: u8, green: u8, blue: u8) {
+        vga_registers::write_dac(index, &[[red, green, blue]]);
+    }
+
+    /// Sets consecutive DAC colors starting at `start`
+    pub fn set_palette(&mut self, start: u8, colors: &[[u8; 3]]) {
+        vga_registers::write_dac(start, colors);
+    }
+}
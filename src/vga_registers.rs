@@ -0,0 +1,230 @@
+//! Port I/O helpers for the VGA registers, shared by the text and graphics drivers.
+//!
+//! Most register groups are indexed: the register number is written to an index port and its
+//! value is then read from or written to the data port next to it. The attribute controller is
+//! the exception, it uses a flip-flop on a single port, see `write_attribute`.
+
+use x86_64::instructions::port::Port;
+
+const MISC_WRITE: u16 = 0x3c2;
+#[allow(dead_code)]
+const MISC_READ: u16 = 0x3cc;
+const SEQUENCER_INDEX: u16 = 0x3c4;
+const SEQUENCER_DATA: u16 = 0x3c5;
+const CRTC_INDEX: u16 = 0x3d4;
+const CRTC_DATA: u16 = 0x3d5;
+const GRAPHICS_INDEX: u16 = 0x3ce;
+const GRAPHICS_DATA: u16 = 0x3cf;
+const ATTRIBUTE_WRITE: u16 = 0x3c0;
+const ATTRIBUTE_READ: u16 = 0x3c1;
+/// Reading it resets the attribute controller flip-flop to "index"
+const INPUT_STATUS_1: u16 = 0x3da;
+const DAC_READ_INDEX: u16 = 0x3c7;
+const DAC_WRITE_INDEX: u16 = 0x3c8;
+const DAC_DATA: u16 = 0x3c9;
+
+/// Palette Address Source bit of the attribute index, the display is blanked while it is clear
+const PALETTE_ADDRESS_SOURCE: u8 = 0x20;
+/// The attribute registers below this index are the 16 palette registers
+const ATTRIBUTE_PALETTE_SIZE: u8 = 0x10;
+
+fn read_indexed(index_port: u16, data_port: u16, index: u8) -> u8 {
+    let mut index_port = Port::new(index_port);
+    let mut data_port = Port::new(data_port);
+    unsafe {
+        index_port.write(index);
+        data_port.read()
+    }
+}
+
+fn write_indexed(index_port: u16, data_port: u16, index: u8, value: u8) {
+    let mut index_port = Port::new(index_port);
+    let mut data_port = Port::new(data_port);
+    unsafe {
+        index_port.write(index);
+        data_port.write(value);
+    }
+}
+
+#[allow(dead_code)]
+pub fn read_misc() -> u8 {
+    unsafe { Port::new(MISC_READ).read() }
+}
+
+pub fn write_misc(value: u8) {
+    unsafe { Port::new(MISC_WRITE).write(value) }
+}
+
+pub fn read_sequencer(index: u8) -> u8 {
+    read_indexed(SEQUENCER_INDEX, SEQUENCER_DATA, index)
+}
+
+pub fn write_sequencer(index: u8, value: u8) {
+    write_indexed(SEQUENCER_INDEX, SEQUENCER_DATA, index, value);
+}
+
+pub fn read_crtc(index: u8) -> u8 {
+    read_indexed(CRTC_INDEX, CRTC_DATA, index)
+}
+
+pub fn write_crtc(index: u8, value: u8) {
+    write_indexed(CRTC_INDEX, CRTC_DATA, index, value);
+}
+
+pub fn read_graphics(index: u8) -> u8 {
+    read_indexed(GRAPHICS_INDEX, GRAPHICS_DATA, index)
+}
+
+pub fn write_graphics(index: u8, value: u8) {
+    write_indexed(GRAPHICS_INDEX, GRAPHICS_DATA, index, value);
+}
+
+/// Selects an attribute controller register, leaving the display on unless it is a palette register
+fn select_attribute(index: u8) {
+    let mut status: Port<u8> = Port::new(INPUT_STATUS_1);
+    let mut attribute = Port::new(ATTRIBUTE_WRITE);
+    // The palette registers can only be accessed while the display is blanked
+    let source = if index < ATTRIBUTE_PALETTE_SIZE { 0 } else { PALETTE_ADDRESS_SOURCE };
+    unsafe {
+        status.read();
+        attribute.write(index | source);
+    }
+}
+
+/// Turns the display back on after a palette register access
+fn enable_display() {
+    let mut status: Port<u8> = Port::new(INPUT_STATUS_1);
+    let mut attribute = Port::new(ATTRIBUTE_WRITE);
+    unsafe {
+        status.read();
+        attribute.write(PALETTE_ADDRESS_SOURCE);
+    }
+}
+
+pub fn read_attribute(index: u8) -> u8 {
+    select_attribute(index);
+    let value = unsafe { Port::new(ATTRIBUTE_READ).read() };
+    enable_display();
+    value
+}
+
+/// Writes an attribute controller register, the index and the value go to the same port
+pub fn write_attribute(index: u8, value: u8) {
+    select_attribute(index);
+    unsafe { Port::new(ATTRIBUTE_WRITE).write(value) }
+    enable_display();
+}
+
+/// Programs the DAC entries from `start` on with 6-bit `[red, green, blue]` values
+pub fn write_dac(start: u8, colors: &[[u8; 3]]) {
+    let mut index = Port::new(DAC_WRITE_INDEX);
+    let mut data: Port<u8> = Port::new(DAC_DATA);
+    unsafe {
+        index.write(start);
+        // The DAC index auto-increments after each blue component
+        for color in colors {
+            for &component in color {
+                data.write(component & 0x3f);
+            }
+        }
+    }
+}
+
+/// Reads the DAC entries from `start` on into `colors`
+pub fn read_dac(start: u8, colors: &mut [[u8; 3]]) {
+    let mut index = Port::new(DAC_READ_INDEX);
+    let mut data: Port<u8> = Port::new(DAC_DATA);
+    unsafe {
+        index.write(start);
+        for color in colors {
+            for component in color {
+                *component = data.read();
+            }
+        }
+    }
+}
+
+/// A complete set of register values describing a video mode
+pub struct ModeRegisters {
+    pub misc: u8,
+    pub sequencer: [u8; 5],
+    pub crtc: [u8; 25],
+    pub graphics: [u8; 9],
+    pub attribute: [u8; 21],
+}
+
+/// Programs every register of `mode`
+pub fn set_mode_registers(mode: &ModeRegisters) {
+    write_misc(mode.misc);
+    for (index, &value) in mode.sequencer.iter().enumerate() {
+        write_sequencer(index as u8, value);
+    }
+
+    // CRTC registers 0-7 are write protected by bit 7 of the vertical retrace end register (0x11),
+    // bit 7 of the horizontal blanking end register (0x03) has to stay set
+    write_crtc(0x03, read_crtc(0x03) | 0x80);
+    write_crtc(0x11, read_crtc(0x11) & !0x80);
+    for (index, &value) in mode.crtc.iter().enumerate() {
+        let value = match index {
+            0x03 => value | 0x80,
+            0x11 => value & !0x80,
+            _ => value,
+        };
+        write_crtc(index as u8, value);
+    }
+
+    for (index, &value) in mode.graphics.iter().enumerate() {
+        write_graphics(index as u8, value);
+    }
+    for (index, &value) in mode.attribute.iter().enumerate() {
+        write_attribute(index as u8, value);
+    }
+}
+
+/// Gives the CPU flat access to `plane` of video memory at 0xA0000, for as long as the guard lives
+///
+/// This is how the font in plane 2 is reached, or the planes clobbered by a graphics mode.
+pub struct PlaneAccess {
+    sequencer_map_mask: u8,
+    sequencer_memory_mode: u8,
+    graphics_read_map: u8,
+    graphics_mode: u8,
+    graphics_misc: u8,
+}
+
+impl PlaneAccess {
+    pub fn new(plane: u8) -> PlaneAccess {
+        let saved = PlaneAccess {
+            sequencer_map_mask: read_sequencer(0x02),
+            sequencer_memory_mode: read_sequencer(0x04),
+            graphics_read_map: read_graphics(0x04),
+            graphics_mode: read_graphics(0x05),
+            graphics_misc: read_graphics(0x06),
+        };
+        // Write and read only `plane`
+        write_sequencer(0x02, 1 << plane);
+        write_graphics(0x04, plane);
+        // Sequential addressing instead of odd/even, no chain-4
+        write_sequencer(0x04, 0x06);
+        // Write mode 0, read mode 0, no odd/even
+        write_graphics(0x05, 0x00);
+        // 64K window at 0xA0000, no chaining
+        write_graphics(0x06, 0x04);
+        saved
+    }
+
+    /// The plane, seen as memory
+    pub fn memory(&mut self) -> *mut u8 {
+        0xa0000 as *mut u8
+    }
+}
+
+impl Drop for PlaneAccess {
+    fn drop(&mut self) {
+        write_sequencer(0x02, self.sequencer_map_mask);
+        write_sequencer(0x04, self.sequencer_memory_mode);
+        write_graphics(0x04, self.graphics_read_map);
+        write_graphics(0x05, self.graphics_mode);
+        write_graphics(0x06, self.graphics_misc);
+    }
+}
@@ -0,0 +1,134 @@
+#!/usr/bin/env python3
+"""Converts a BDF bitmap font into a PSF2 font laid out in code page 437 order.
+
+Glyph N of the output is the character with CP437 code N, so the font can be uploaded to the VGA
+character generator as is, and the PSF2 unicode table maps each glyph back to its character for
+the framebuffer console. Glyphs narrower than 8 pixels or shorter than the target height are
+centered; box-drawing and block characters are stretched instead so that they keep connecting with
+their neighbours.
+
+    tools/bdf2psf.py 8x13.bdf fonts/cp437-8x16.psf --height 16
+    tools/bdf2psf.py 5x8.bdf fonts/cp437-8x8.psf --height 8
+"""
+
+import argparse
+import struct
+import sys
+
+PSF2_MAGIC = 0x864AB572
+PSF2_HAS_UNICODE_TABLE = 0x01
+PSF2_SEPARATOR = 0xFF
+
+
+def cp437_chars():
+    low = "\0☺☻♥♦♣♠•◘○◙♂♀♪♫☼►◄↕‼¶§▬↨↑↓→←∟↔▲▼"
+    ascii_part = "".join(chr(c) for c in range(0x20, 0x7F)) + "⌂"
+    high = bytes(range(0x80, 0x100)).decode("cp437")
+    return low + ascii_part + high
+
+
+def parse_bdf(path):
+    glyphs = {}
+    ascent = descent = width = None
+    with open(path, encoding="latin-1") as f:
+        lines = iter(f.read().splitlines())
+    for line in lines:
+        if line.startswith("FONTBOUNDINGBOX"):
+            width = int(line.split()[1])
+        elif line.startswith("FONT_ASCENT"):
+            ascent = int(line.split()[1])
+        elif line.startswith("FONT_DESCENT"):
+            descent = int(line.split()[1])
+        elif line.startswith("STARTCHAR"):
+            encoding, bbx, rows = None, None, []
+            for line in lines:
+                if line.startswith("ENCODING"):
+                    encoding = int(line.split()[1])
+                elif line.startswith("BBX"):
+                    bbx = tuple(int(v) for v in line.split()[1:])
+                elif line.startswith("BITMAP"):
+                    for line in lines:
+                        if line.startswith("ENDCHAR"):
+                            break
+                        # Rows are padded to whole bytes, only the leftmost one matters here
+                        rows.append(int(line[:2].ljust(2, "0"), 16))
+                    break
+            glyphs[encoding] = (bbx, rows)
+    return glyphs, ascent, descent, width
+
+
+def render(glyph, ascent, descent, width):
+    """Returns the glyph as `ascent + descent` rows of `width` bits, MSB first"""
+    (w, h, xoff, yoff), rows = glyph
+    cell = [0] * (ascent + descent)
+    for i, bits in enumerate(rows):
+        y = ascent - (yoff + h) + i
+        if 0 <= y < len(cell):
+            cell[y] |= (bits >> max(xoff, 0)) & ((0xFF << (8 - width)) & 0xFF)
+    return cell
+
+
+def stretchable(char):
+    return 0x2500 <= ord(char) <= 0x259F
+
+
+def widen(row, width, stretch):
+    """Centers a row of `width` pixels in 8, repeating the edge pixels outwards if `stretch`"""
+    left = (8 - width) // 2
+    right = 8 - width - left
+    widened = row >> left
+    if stretch:
+        if row & 0x80:
+            widened |= (0xFF << (8 - left)) & 0xFF
+        if row & (0x80 >> (width - 1)):
+            widened |= 0xFF >> (8 - right)
+    return widened
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("bdf")
+    parser.add_argument("psf")
+    parser.add_argument("--height", type=int, default=16)
+    args = parser.parse_args()
+
+    glyphs, ascent, descent, width = parse_bdf(args.bdf)
+    if width > 8:
+        sys.exit("text mode glyphs are 8 pixels wide at most")
+    source_height = ascent + descent
+    padding = args.height - source_height
+    top = padding // 2
+    bottom = padding - top
+
+    bitmaps = []
+    unicode_table = bytearray()
+    missing = []
+    for code, char in enumerate(cp437_chars()):
+        glyph = glyphs.get(ord(char)) if code else None
+        if glyph is None:
+            if code:
+                missing.append(char)
+            cell = [0] * source_height
+        else:
+            cell = render(glyph, ascent, descent, width)
+            cell = [widen(row, width, stretchable(char)) for row in cell]
+        if stretchable(char):
+            cell = [cell[0]] * top + cell + [cell[-1]] * bottom
+        else:
+            cell = [0] * top + cell + [0] * bottom
+        bitmaps.append(bytes(cell))
+        if code:
+            unicode_table += char.encode("utf-8")
+        unicode_table.append(PSF2_SEPARATOR)
+
+    header = struct.pack(
+        "<IIIIIIII", PSF2_MAGIC, 0, 32, PSF2_HAS_UNICODE_TABLE, 256, args.height, args.height, 8
+    )
+    with open(args.psf, "wb") as f:
+        f.write(header + b"".join(bitmaps) + unicode_table)
+    if missing:
+        print("no glyph for:", " ".join(missing), file=sys.stderr)
+
+
+if __name__ == "__main__":
+    main()
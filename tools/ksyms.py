@@ -0,0 +1,108 @@
+#!/usr/bin/env python3
+"""Fills the `.ksyms` section of a linked kernel with its function symbols, for the backtraces.
+
+The kernel reserves the section with an empty table (see `src/ksyms.rs` for the layout), this
+reads the function addresses, sizes and demangled names with `nm` and writes the table over it in
+place, so the ELF file keeps its layout. It has to run after every link, before the boot image is
+built. `cargo run` does both through `tools/runner.sh`, otherwise:
+
+    cargo build
+    tools/ksyms.py target/x86_64-basic_os/debug/basic_os
+    cargo bootimage
+"""
+
+import argparse
+import re
+import struct
+import subprocess
+import sys
+
+SECTION = ".ksyms"
+MAGIC = b"KSYM"
+HEADER = struct.Struct("<4sI")
+ENTRY = struct.Struct("<QIII")
+
+# Legacy Rust mangling leaves a hash after the demangled path
+RUST_HASH = re.compile(r"::h[0-9a-f]{16}$")
+
+
+def find_section(elf, name):
+    """Returns the file offset and size of section `name` of a little endian ELF64 file."""
+    if elf[:4] != b"\x7fELF" or elf[4] != 2 or elf[5] != 1:
+        sys.exit("not a little endian ELF64 file")
+    (shoff,) = struct.unpack_from("<Q", elf, 0x28)
+    shentsize, shnum, shstrndx = struct.unpack_from("<HHH", elf, 0x3A)
+
+    def header(index):
+        return struct.unpack_from("<IIQQQQ", elf, shoff + index * shentsize)
+
+    strtab_offset = header(shstrndx)[4]
+    for index in range(shnum):
+        name_offset, _, _, _, offset, size = header(index)
+        start = strtab_offset + name_offset
+        if elf[start:elf.index(b"\0", start)].decode() == name:
+            return offset, size
+    sys.exit(f"no {name} section, is this the kernel?")
+
+
+def function_symbols(path, nm):
+    """Sorted `(address, size, name)` of the functions, one per address.
+
+    The size is 0 for the symbols without one, like those defined in assembly.
+    """
+    output = subprocess.run(
+        [nm, "--defined-only", "--demangle", "--print-size", path],
+        check=True,
+        capture_output=True,
+        text=True,
+    ).stdout
+    symbols = {}
+    for line in output.splitlines():
+        # `address size type name`, or `address type name` without a size
+        parts = line.split(" ", 3)
+        if len(parts) >= 3 and len(parts[1]) == 1:
+            parts = line.split(" ", 2)
+            parts.insert(1, "0")
+        if len(parts) != 4 or parts[2] not in "tTwW":
+            continue
+        address, size = int(parts[0], 16), int(parts[1], 16)
+        # Of the names sharing an address, the one covering the most code is kept
+        if address not in symbols or size > symbols[address][0]:
+            symbols[address] = (size, RUST_HASH.sub("", parts[3]))
+    return [(address, size, name) for address, (size, name) in sorted(symbols.items())]
+
+
+def build_table(symbols, size):
+    names_offset = HEADER.size + len(symbols) * ENTRY.size
+    entries = bytearray(HEADER.pack(MAGIC, len(symbols)))
+    names = bytearray()
+    for address, symbol_size, name in symbols:
+        encoded = name.encode()
+        entries += ENTRY.pack(address, symbol_size, names_offset + len(names), len(encoded))
+        names += encoded
+    table = entries + names
+    if len(table) > size:
+        sys.exit(f"the symbols take {len(table)} bytes, raise KSYMS_SIZE above {size}")
+    return table + bytes(size - len(table))
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    parser.add_argument("kernel", help="linked kernel ELF file, patched in place")
+    parser.add_argument("--nm", default="nm", help="nm binary understanding the kernel (default: nm)")
+    args = parser.parse_args()
+
+    with open(args.kernel, "rb") as f:
+        elf = bytearray(f.read())
+    offset, size = find_section(elf, SECTION)
+    if elf[offset:offset + len(MAGIC)] != MAGIC:
+        sys.exit(f"the {SECTION} section doesn't start with the table magic")
+    symbols = function_symbols(args.kernel, args.nm)
+    elf[offset:offset + size] = build_table(symbols, size)
+    with open(args.kernel, "wb") as f:
+        f.write(elf)
+    print(f"{len(symbols)} symbols written to {SECTION}")
+
+
+if __name__ == "__main__":
+    main()
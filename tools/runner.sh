@@ -0,0 +1,6 @@
+#!/bin/sh
+# Runner of `cargo run`: fills the .ksyms table of the kernel cargo just linked, then has
+# bootimage build the boot image and start QEMU with it.
+set -e
+"$(dirname "$0")/ksyms.py" "$1"
+exec bootimage runner "$@"
@@ -0,0 +1,177 @@
+#!/usr/bin/env python3
+"""Runs the screen test scenarios in QEMU and compares their captures with golden files.
+
+A kernel built with the `screen-tests` feature runs the scenarios of `src/screen_tests.rs`, sends a
+capture of the screen after each one over the serial port (the format is described in
+`src/vga_buffer/capture.rs`) and exits QEMU. Each capture is compared with
+`tests/screens/<name>.txt`. The checks the kernel runs afterwards report `CHECK <name> ok` or
+`CHECK <name> FAILED: <reason>`, a failed one fails the run as well:
+
+    cargo bootimage --features screen-tests
+    tools/screen_test.py target/x86_64-basic_os/debug/bootimage-basic_os.bin
+
+`--update` saves the captures as the new golden files instead of comparing, after a change to the
+`Writer` that is meant to change the output. `--log` checks a serial log saved earlier instead of
+running QEMU.
+"""
+
+import argparse
+import difflib
+import os
+import re
+import subprocess
+import sys
+
+GOLDEN_DIR = os.path.join(os.path.dirname(os.path.abspath(__file__)), "..", "tests", "screens")
+
+QEMU_ARGS = [
+    "-serial", "stdio",
+    "-display", "none",
+    "-no-reboot",
+    "-device", "isa-debug-exit,iobase=0xf4,iosize=0x04",
+]
+# `qemu::ExitCode::Success` (0x10) and `Failed` (0x11) as QEMU reports them, `(code << 1) | 1`
+QEMU_SUCCESS = 0x21
+QEMU_FAILED = 0x23
+
+HEADER = re.compile(r"^SCREEN (\S+) (\d+)x(\d+)$")
+CHECK = re.compile(r"^CHECK (\S+) (ok|FAILED: (.*))$")
+
+
+def run_qemu(image, qemu, timeout):
+    """Boots `image` and returns everything the kernel sent to the serial port."""
+    command = [qemu, "-drive", f"format=raw,file={image}"] + QEMU_ARGS
+    try:
+        result = subprocess.run(command, capture_output=True, timeout=timeout)
+    except subprocess.TimeoutExpired as error:
+        print(f"QEMU still running after {timeout}s, stopped it", file=sys.stderr)
+        return (error.stdout or b"").decode("utf-8", "replace")
+    if result.returncode not in (QEMU_SUCCESS, QEMU_FAILED):
+        print(f"QEMU exited with {result.returncode}, the kernel didn't finish the scenarios",
+              file=sys.stderr)
+    return result.stdout.decode("utf-8", "replace")
+
+
+def parse_captures(log):
+    """Maps each capture name to its block of lines, from `SCREEN` to `END` included."""
+    captures = {}
+    lines = iter(log.replace("\r\n", "\n").split("\n"))
+    for line in lines:
+        match = HEADER.match(line)
+        if not match:
+            continue
+        name, rows = match.group(1), int(match.group(3))
+        block = [line]
+        for line in lines:
+            block.append(line)
+            if line == "END":
+                break
+        # A row of text and a row of attributes per screen row, between the header and `END`
+        if len(block) != 2 * rows + 2 or block[-1] != "END":
+            print(f"{name}: incomplete capture", file=sys.stderr)
+            continue
+        captures[name] = "\n".join(block) + "\n"
+    return captures
+
+
+def parse_checks(log):
+    """Maps each check name to `None` if it passed, or to the reason it failed."""
+    checks = {}
+    for line in log.replace("\r\n", "\n").split("\n"):
+        match = CHECK.match(line)
+        if match:
+            checks[match.group(1)] = match.group(3)
+    return checks
+
+
+def report_checks(checks):
+    """Prints the outcome of every check, returns the failures."""
+    failures = 0
+    for name, reason in sorted(checks.items()):
+        if reason is None:
+            print(f"ok   check {name}")
+        else:
+            print(f"FAIL check {name}: {reason}")
+            failures += 1
+    return failures
+
+
+def golden_path(golden_dir, name):
+    return os.path.join(golden_dir, f"{name}.txt")
+
+
+def compare(captures, golden_dir):
+    """Prints a diff for every capture that differs from its golden file, returns the failures."""
+    passed = failures = 0
+    for name, capture in sorted(captures.items()):
+        path = golden_path(golden_dir, name)
+        if not os.path.exists(path):
+            print(f"FAIL {name}: no golden file {path}, run with --update to create it")
+            failures += 1
+            continue
+        with open(path, encoding="utf-8") as f:
+            golden = f.read()
+        if golden == capture:
+            print(f"ok   {name}")
+            passed += 1
+            continue
+        print(f"FAIL {name}")
+        sys.stdout.writelines(difflib.unified_diff(
+            golden.splitlines(keepends=True),
+            capture.splitlines(keepends=True),
+            fromfile=path,
+            tofile=f"{name} (captured)",
+        ))
+        failures += 1
+    # A scenario that stopped being captured is a failure too
+    if os.path.isdir(golden_dir):
+        for file_name in sorted(os.listdir(golden_dir)):
+            name, extension = os.path.splitext(file_name)
+            if extension == ".txt" and name not in captures:
+                print(f"FAIL {name}: not captured")
+                failures += 1
+    print(f"{passed} passed, {failures} failed")
+    return failures
+
+
+def update(captures, golden_dir):
+    os.makedirs(golden_dir, exist_ok=True)
+    for name, capture in sorted(captures.items()):
+        with open(golden_path(golden_dir, name), "w", encoding="utf-8") as f:
+            f.write(capture)
+        print(f"saved {name}")
+
+
+def main():
+    parser = argparse.ArgumentParser(description=__doc__.splitlines()[0])
+    source = parser.add_mutually_exclusive_group(required=True)
+    source.add_argument("image", nargs="?", help="boot image built with --features screen-tests")
+    source.add_argument("--log", help="serial output saved from an earlier run")
+    parser.add_argument("--update", action="store_true", help="save the captures as golden files")
+    parser.add_argument("--golden", default=GOLDEN_DIR, help="golden file directory")
+    parser.add_argument("--qemu", default="qemu-system-x86_64", help="QEMU binary")
+    parser.add_argument("--timeout", type=float, default=60, help="seconds before QEMU is stopped")
+    args = parser.parse_args()
+
+    if args.log:
+        with open(args.log, encoding="utf-8", errors="replace") as f:
+            log = f.read()
+    else:
+        log = run_qemu(args.image, args.qemu, args.timeout)
+    captures = parse_captures(log)
+    if not captures:
+        sys.stderr.write("no screen captures in the serial output, its end was:\n")
+        sys.stderr.write("\n".join(log.splitlines()[-20:]) + "\n")
+        sys.exit(1)
+
+    checks = parse_checks(log)
+
+    if args.update:
+        update(captures, args.golden)
+        sys.exit(1 if report_checks(checks) else 0)
+    failures = compare(captures, args.golden) + report_checks(checks)
+    sys.exit(1 if failures else 0)
+
+
+if __name__ == "__main__":
+    main()